@@ -0,0 +1,648 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, Weak},
+    task::{ready, Context, Poll, Waker},
+};
+
+use serde::de::DeserializeOwned;
+use zbus::{
+    export::futures_core::Stream,
+    export::futures_util::StreamExt,
+    message::{self, Message},
+    zvariant::{ObjectPath, Optional, OwnedObjectPath, Type},
+    Connection, MatchRule, MessageStream, Proxy, ProxyDefault,
+};
+
+use crate::{
+    AddressResolverProxy, DnsClass, DnsType, DomainBrowserProxy, DomainBrowserType, DomainItem,
+    HostNameResolverProxy, InterfaceIndex, LookupFlags, Protocol, RecordBrowserProxy, RecordItem,
+    ResolveAddressResponse, ResolveHostNameResponse, ResolveServiceResponse, Server2Proxy,
+    ServiceBrowserProxy, ServiceItem, ServiceResolverProxy, ServiceTypeBrowserProxy,
+    ServiceTypeItem,
+};
+
+/// The signals of a browser or resolver, merged into a single event.
+///
+/// Resolvers report each result they found as `New` and never emit the other browse events.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BrowseEvent<T> {
+    /// An item appeared, or a resolver found its result.
+    New(T),
+    /// A previously reported item disappeared.
+    Remove(T),
+    /// All entries of the cache have been reported.
+    CacheExhausted,
+    /// No more entries are expected to show up in the near future.
+    AllForNow,
+    /// Browsing or resolving failed, no further events follow.
+    Failure(String),
+    /// A signal could not be received or decoded and was dropped, further events may follow.
+    Error(String),
+}
+
+impl<T> BrowseEvent<T>
+where
+    T: DeserializeOwned + Type,
+{
+    /// Maps a signal to its event, `None` for signals not emitted by browsers and resolvers.
+    fn from_message(message: zbus::Result<Message>) -> Option<Self> {
+        let message = match message {
+            Ok(message) => message,
+            Err(e) => return Some(Self::Error(e.to_string())),
+        };
+        let body = message.body();
+
+        let event = match message.header().member()?.as_str() {
+            "ItemNew" | "Found" => body.deserialize().map(Self::New),
+            "ItemRemove" => body.deserialize().map(Self::Remove),
+            "CacheExhausted" => Ok(Self::CacheExhausted),
+            "AllForNow" => Ok(Self::AllForNow),
+            "Failure" => body.deserialize().map(Self::Failure),
+            _ => return None,
+        };
+
+        Some(event.unwrap_or_else(|e| Self::Error(e.to_string())))
+    }
+}
+
+/// A browser or resolver together with the stream of its events.
+///
+/// All signals are received through a single match rule,
+/// so events arrive in the order avahi emitted them.
+pub struct Browse<P, T> {
+    proxy: P,
+    signals: Signals,
+    item: PhantomData<fn() -> T>,
+}
+
+enum Signals {
+    /// Subscribed to the signals of this object alone.
+    Own(Box<MessageStream>),
+    /// Taken from the signals of all objects of its kind, subscribed to before it existed.
+    Shared(Receiver),
+}
+
+pub type ServiceBrowser = Browse<ServiceBrowserProxy<'static>, ServiceItem>;
+pub type ServiceTypeBrowser = Browse<ServiceTypeBrowserProxy<'static>, ServiceTypeItem>;
+pub type DomainBrowser = Browse<DomainBrowserProxy<'static>, DomainItem>;
+pub type RecordBrowser = Browse<RecordBrowserProxy<'static>, RecordItem>;
+pub type HostNameResolver = Browse<HostNameResolverProxy<'static>, ResolveHostNameResponse>;
+pub type AddressResolver = Browse<AddressResolverProxy<'static>, ResolveAddressResponse>;
+pub type ServiceResolver = Browse<ServiceResolverProxy<'static>, ResolveServiceResponse>;
+
+impl<'p, P, T> Browse<P, T>
+where
+    P: AsRef<Proxy<'p>>,
+{
+    /// Subscribes to the signals of an existing browser or resolver.
+    /// Events emitted before are lost, use [`Server`](crate::Server) to subscribe in time.
+    pub async fn new(proxy: P) -> zbus::Result<Self> {
+        let inner = proxy.as_ref();
+        let rule = MatchRule::builder()
+            .msg_type(message::Type::Signal)
+            .sender(inner.destination())?
+            .path(inner.path())?
+            .interface(inner.interface())?
+            .build();
+        let signals = MessageStream::for_match_rule(rule, inner.connection(), None).await?;
+
+        Ok(Self {
+            proxy,
+            signals: Signals::Own(Box::new(signals)),
+            item: PhantomData,
+        })
+    }
+}
+
+impl<P, T> Browse<P, T> {
+    /// Pairs an object with its signals from a [`Subscription`] made before it was created.
+    pub(crate) fn with_signals(proxy: P, signals: Receiver) -> Self {
+        Self {
+            proxy,
+            signals: Signals::Shared(signals),
+            item: PhantomData,
+        }
+    }
+}
+
+/// The subscriptions to the signals of each kind of browser or resolver,
+/// alive as long as any object of the kind is.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Subscriptions(Arc<Mutex<HashMap<&'static str, WeakDispatch>>>);
+
+type WeakDispatch = Weak<Mutex<Dispatch>>;
+
+impl Subscriptions {
+    /// The subscription to the signals of every object of one kind, shared by all of them.
+    pub(crate) async fn get<P>(&self, connection: &Connection) -> zbus::Result<Subscription>
+    where
+        P: ProxyDefault,
+    {
+        let interface = P::INTERFACE.unwrap_or_default();
+        let existing = lock(&self.0).get(interface).and_then(Weak::upgrade);
+        // a subscription whose signals ended cannot serve new objects
+        if let Some(dispatch) = existing.filter(|dispatch| lock(dispatch).queues.end.is_none()) {
+            return Ok(Subscription(dispatch));
+        }
+
+        let mut rule = MatchRule::builder().msg_type(message::Type::Signal);
+        if let Some(destination) = P::DESTINATION {
+            rule = rule.sender(destination)?;
+        }
+        if let Some(interface) = P::INTERFACE {
+            rule = rule.interface(interface)?;
+        }
+        let signals = MessageStream::for_match_rule(rule.build(), connection, None).await?;
+
+        let dispatch = Arc::new(Mutex::new(Dispatch {
+            signals,
+            queues: Queues::default(),
+        }));
+        lock(&self.0).insert(interface, Arc::downgrade(&dispatch));
+        Ok(Subscription(dispatch))
+    }
+}
+
+/// The signals of every browser or resolver of one kind, received through a single match rule
+/// and handed to each object by its path.
+pub(crate) struct Subscription(Arc<Mutex<Dispatch>>);
+
+impl Subscription {
+    /// Keeps the signals of new objects until the one being created claims its own.
+    pub(crate) fn creating(&self) -> Creating {
+        lock(&self.0).queues.creating += 1;
+        Creating(self.0.clone())
+    }
+}
+
+#[derive(Debug)]
+struct Dispatch {
+    signals: MessageStream,
+    queues: Queues,
+}
+
+/// Signals received for an object while another one polled the subscription.
+#[derive(Debug, Default)]
+struct Queues {
+    /// Objects being created, whose path is not known yet.
+    creating: usize,
+    queues: HashMap<OwnedObjectPath, Queue>,
+    /// How the signals ended, reported to every object once its queue is empty.
+    end: Option<End>,
+}
+
+#[derive(Debug, Clone)]
+enum End {
+    Closed,
+    Failed(zbus::Error),
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    messages: VecDeque<Message>,
+    /// Whether a [`Receiver`] owns the queue, otherwise its object may still be created.
+    claimed: bool,
+    waker: Option<Waker>,
+}
+
+impl Queues {
+    fn push(&mut self, path: OwnedObjectPath, message: Message) {
+        match self.queues.get_mut(&path) {
+            Some(queue) => {
+                queue.messages.push_back(message);
+                if let Some(waker) = queue.waker.take() {
+                    waker.wake();
+                }
+            }
+            None if self.creating > 0 => {
+                let queue = self.queues.entry(path).or_default();
+                queue.messages.push_back(message);
+            }
+            // the object was freed already
+            None => (),
+        }
+    }
+
+    fn pop(&mut self, path: &OwnedObjectPath) -> Option<Message> {
+        self.queues.get_mut(path)?.messages.pop_front()
+    }
+
+    fn claim(&mut self, path: OwnedObjectPath) {
+        self.queues.entry(path).or_default().claimed = true;
+    }
+
+    /// Drops the signals of objects which nobody claimed once no object is being created.
+    fn created(&mut self) {
+        self.creating -= 1;
+        if self.creating == 0 {
+            self.queues.retain(|_, queue| queue.claimed);
+        }
+    }
+
+    fn wait(&mut self, path: &OwnedObjectPath, waker: &Waker) {
+        if let Some(queue) = self.queues.get_mut(path) {
+            queue.waker = Some(waker.clone());
+        }
+    }
+
+    fn remove(&mut self, path: &OwnedObjectPath) {
+        self.queues.remove(path);
+
+        // the object may have been the one waiting on the subscription
+        self.wake();
+    }
+
+    /// Records the end of the signals, which no object waiting for its queue would notice.
+    fn finish(&mut self, end: End) {
+        self.end = Some(end);
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        for queue in self.queues.values_mut() {
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// An object being created, see [`Subscription::creating`].
+pub(crate) struct Creating(Arc<Mutex<Dispatch>>);
+
+impl Creating {
+    /// The signals of the created object, including the ones it emitted before.
+    pub(crate) fn claim(self, path: &ObjectPath<'_>) -> Receiver {
+        let path = OwnedObjectPath::from(path.to_owned());
+        lock(&self.0).queues.claim(path.clone());
+
+        Receiver {
+            dispatch: self.0.clone(),
+            path,
+            ended: false,
+        }
+    }
+}
+
+impl Drop for Creating {
+    fn drop(&mut self) {
+        lock(&self.0).queues.created();
+    }
+}
+
+/// The signals of a single object, taken from a shared [`Subscription`].
+///
+/// Whichever object polls the subscription queues the signals of the others and wakes them.
+pub(crate) struct Receiver {
+    dispatch: Arc<Mutex<Dispatch>>,
+    path: OwnedObjectPath,
+    /// Whether the end of the signals was reported to this object.
+    ended: bool,
+}
+
+impl Receiver {
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<zbus::Result<Message>>> {
+        let mut dispatch = lock(&self.dispatch);
+        let Dispatch { signals, queues } = &mut *dispatch;
+
+        if let Some(message) = queues.pop(&self.path) {
+            return Poll::Ready(Some(Ok(message)));
+        }
+
+        loop {
+            if let Some(end) = &queues.end {
+                // each object sees the error once, like the stream it shares
+                let ended = std::mem::replace(&mut self.ended, true);
+                return Poll::Ready(match end {
+                    End::Failed(e) if !ended => Some(Err(e.clone())),
+                    _ => None,
+                });
+            }
+
+            let message = match signals.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(message))) => message,
+                // errors and the end of the stream are not tied to an object, so all objects see them
+                Poll::Ready(Some(Err(e))) => {
+                    queues.finish(End::Failed(e));
+                    continue;
+                }
+                Poll::Ready(None) => {
+                    queues.finish(End::Closed);
+                    continue;
+                }
+                Poll::Pending => {
+                    queues.wait(&self.path, cx.waker());
+                    return Poll::Pending;
+                }
+            };
+
+            let Some(path) = message.header().path().map(|path| path.to_owned().into()) else {
+                continue;
+            };
+            if path == self.path {
+                return Poll::Ready(Some(Ok(message)));
+            }
+            queues.push(path, message);
+        }
+    }
+}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        lock(&self.dispatch).queues.remove(&self.path);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl<P, T> Browse<P, T> {
+    /// The browser or resolver, e.g. to free it once done.
+    pub fn proxy(&self) -> &P {
+        &self.proxy
+    }
+
+    /// Stops receiving events and returns the browser or resolver.
+    pub fn into_proxy(self) -> P {
+        self.proxy
+    }
+
+    /// Wraps the browser or resolver, e.g. in a handle owning it, keeping the events.
+    pub fn map_proxy<Q>(self, f: impl FnOnce(P) -> Q) -> Browse<Q, T> {
+        Browse {
+            proxy: f(self.proxy),
+            signals: self.signals,
+            item: PhantomData,
+        }
+    }
+}
+
+impl<P, T> Stream for Browse<P, T>
+where
+    P: Unpin,
+    T: DeserializeOwned + Type,
+{
+    type Item = BrowseEvent<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let message = match &mut self.signals {
+                Signals::Own(signals) => ready!(signals.poll_next_unpin(cx)),
+                Signals::Shared(signals) => ready!(signals.poll_next(cx)),
+            };
+            let Some(message) = message else {
+                return Poll::Ready(None);
+            };
+            if let Some(event) = BrowseEvent::from_message(message) {
+                return Poll::Ready(Some(event));
+            }
+        }
+    }
+}
+
+/// Creates the proxy of a prepared browser or resolver,
+/// subscribes to its signals and only then starts it.
+macro_rules! start {
+    ($server:expr, $proxy:ident, $path:expr) => {{
+        let proxy = $proxy::new($server.inner().connection(), $path).await?;
+        let browse = Browse::new(proxy).await?;
+        browse.proxy().start().await?;
+        Ok(browse)
+    }};
+}
+
+impl Server2Proxy<'_> {
+    pub(crate) async fn service_browser(
+        &self,
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        type_: &str,
+        domain: &str,
+        flags: LookupFlags,
+    ) -> zbus::Result<ServiceBrowser> {
+        let path = self
+            .service_browser_prepare(interface, protocol, type_, domain, flags)
+            .await?;
+        start!(self, ServiceBrowserProxy, path)
+    }
+
+    pub(crate) async fn service_type_browser(
+        &self,
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        domain: &str,
+        flags: LookupFlags,
+    ) -> zbus::Result<ServiceTypeBrowser> {
+        let path = self
+            .service_type_browser_prepare(interface, protocol, domain, flags)
+            .await?;
+        start!(self, ServiceTypeBrowserProxy, path)
+    }
+
+    pub(crate) async fn domain_browser(
+        &self,
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        domain: &str,
+        btype: DomainBrowserType,
+        flags: LookupFlags,
+    ) -> zbus::Result<DomainBrowser> {
+        let path = self
+            .domain_browser_prepare(interface, protocol, domain, btype, flags)
+            .await?;
+        start!(self, DomainBrowserProxy, path)
+    }
+
+    pub(crate) async fn record_browser(
+        &self,
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        name: &str,
+        clazz: DnsClass,
+        type_: DnsType,
+        flags: LookupFlags,
+    ) -> zbus::Result<RecordBrowser> {
+        let path = self
+            .record_browser_prepare(interface, protocol, name, clazz, type_, flags)
+            .await?;
+        start!(self, RecordBrowserProxy, path)
+    }
+
+    pub(crate) async fn host_name_resolver(
+        &self,
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        name: &str,
+        aprotocol: Protocol,
+        flags: LookupFlags,
+    ) -> zbus::Result<HostNameResolver> {
+        let path = self
+            .host_name_resolver_prepare(interface, protocol, name, aprotocol, flags)
+            .await?;
+        start!(self, HostNameResolverProxy, path)
+    }
+
+    pub(crate) async fn address_resolver(
+        &self,
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        address: &str,
+        flags: LookupFlags,
+    ) -> zbus::Result<AddressResolver> {
+        let path = self
+            .address_resolver_prepare(interface, protocol, address, flags)
+            .await?;
+        start!(self, AddressResolverProxy, path)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn service_resolver(
+        &self,
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        name: &str,
+        type_: &str,
+        domain: &str,
+        aprotocol: Protocol,
+        flags: LookupFlags,
+    ) -> zbus::Result<ServiceResolver> {
+        let path = self
+            .service_resolver_prepare(interface, protocol, name, type_, domain, aprotocol, flags)
+            .await?;
+        start!(self, ServiceResolverProxy, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::{message::Message, zvariant::Optional};
+
+    use zbus::zvariant::OwnedObjectPath;
+
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{Wake, Waker},
+    };
+
+    use super::{BrowseEvent, End, Queues};
+    use crate::{LookupResultFlags, Protocol, ServiceTypeItem};
+
+    const PATH: &str = "/Client1/ServiceTypeBrowser1";
+    const INTERFACE: &str = "org.freedesktop.Avahi.ServiceTypeBrowser";
+
+    fn signal<B>(member: &str, body: &B) -> zbus::Result<Message>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        Message::signal(PATH, INTERFACE, member)?.build(body)
+    }
+
+    #[test]
+    fn from_message() {
+        let item = ServiceTypeItem {
+            interface: Optional::default(),
+            protocol: Protocol::Inet,
+            type_: "_http._tcp".to_owned(),
+            domain: "local".to_owned(),
+            flags: LookupResultFlags::empty(),
+        };
+        let event = |message| BrowseEvent::<ServiceTypeItem>::from_message(message);
+
+        assert_eq!(
+            event(signal("ItemNew", &item)),
+            Some(BrowseEvent::New(item.clone()))
+        );
+        assert_eq!(
+            event(signal("ItemRemove", &item)),
+            Some(BrowseEvent::Remove(item.clone()))
+        );
+        assert_eq!(
+            event(signal("CacheExhausted", &())),
+            Some(BrowseEvent::CacheExhausted)
+        );
+        assert_eq!(
+            event(signal("AllForNow", &())),
+            Some(BrowseEvent::AllForNow)
+        );
+        assert_eq!(
+            event(signal("Failure", &"Timeout reached")),
+            Some(BrowseEvent::Failure("Timeout reached".to_owned()))
+        );
+        assert_eq!(event(signal("StateChanged", &())), None);
+
+        assert!(matches!(
+            event(signal("ItemNew", &"_http._tcp")),
+            Some(BrowseEvent::Error(_))
+        ));
+        assert!(matches!(
+            event(Err(zbus::Error::Failure("gone".to_owned()))),
+            Some(BrowseEvent::Error(_))
+        ));
+    }
+
+    #[test]
+    fn queues() {
+        let path = |path: &str| OwnedObjectPath::try_from(path).unwrap();
+        let first = path("/Client1/ServiceTypeBrowser1");
+        let second = path("/Client1/ServiceTypeBrowser2");
+        let members = |queues: &mut Queues, path| {
+            std::iter::from_fn(|| queues.pop(path))
+                .map(|message| message.header().member().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        let mut queues = Queues::default();
+
+        // signals of unknown objects are dropped unless one is being created
+        queues.push(first.clone(), signal("AllForNow", &()).unwrap());
+        assert!(members(&mut queues, &first).is_empty());
+
+        queues.creating += 1;
+        queues.push(first.clone(), signal("ItemNew", &()).unwrap());
+        queues.push(second.clone(), signal("ItemNew", &()).unwrap());
+        queues.claim(first.clone());
+        queues.created();
+        queues.push(first.clone(), signal("AllForNow", &()).unwrap());
+        queues.push(second.clone(), signal("AllForNow", &()).unwrap());
+
+        assert_eq!(members(&mut queues, &first), ["ItemNew", "AllForNow"]);
+        assert!(members(&mut queues, &second).is_empty());
+
+        queues.remove(&first);
+        queues.push(first.clone(), signal("ItemRemove", &()).unwrap());
+        assert!(members(&mut queues, &first).is_empty());
+    }
+
+    #[test]
+    fn finish() {
+        struct Count(AtomicUsize);
+
+        impl Wake for Count {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let count = Arc::new(Count(AtomicUsize::new(0)));
+        let waker = Waker::from(count.clone());
+        let mut queues = Queues::default();
+        for path in [
+            "/Client1/ServiceTypeBrowser1",
+            "/Client1/ServiceTypeBrowser2",
+        ] {
+            let path = OwnedObjectPath::try_from(path).unwrap();
+            queues.claim(path.clone());
+            queues.wait(&path, &waker);
+        }
+
+        // every waiting object is woken to see the end, not just the one which polled
+        queues.finish(End::Closed);
+        assert_eq!(count.0.load(Ordering::SeqCst), 2);
+        assert!(matches!(queues.end, Some(End::Closed)));
+    }
+}
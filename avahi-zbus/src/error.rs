@@ -0,0 +1,207 @@
+use std::fmt;
+
+/// Defines the errors of avahi with their numeric code, D-Bus name and message.
+macro_rules! avahi_errors {
+    ($($name:ident = $code:literal, $dbus:literal, $message:literal;)*) => {
+        /// An error reported by avahi, numbered as in `avahi-common/error.h`.
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+        #[repr(i32)]
+        pub enum AvahiError {
+            $($name = $code,)*
+        }
+
+        impl AvahiError {
+            /// The numeric code as returned by the avahi C API.
+            pub const fn code(self) -> i32 {
+                self as i32
+            }
+
+            pub const fn from_code(code: i32) -> Option<Self> {
+                match code {
+                    $($code => Some(Self::$name),)*
+                    _ => None,
+                }
+            }
+
+            /// The name of the error on D-Bus, e.g. `org.freedesktop.Avahi.CollisionError`.
+            pub const fn dbus_name(self) -> &'static str {
+                match self {
+                    $(Self::$name => concat!("org.freedesktop.Avahi.", $dbus),)*
+                }
+            }
+
+            pub fn from_dbus_name(name: &str) -> Option<Self> {
+                match name.strip_prefix(DBUS_PREFIX)? {
+                    $($dbus => Some(Self::$name),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl fmt::Display for AvahiError {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let message = match self {
+                    $(Self::$name => $message,)*
+                };
+                f.write_str(message)
+            }
+        }
+    };
+}
+
+const DBUS_PREFIX: &str = "org.freedesktop.Avahi.";
+
+avahi_errors! {
+    Failure = -1, "Failure", "Operation failed";
+    BadState = -2, "BadStateError", "Bad state";
+    InvalidHostName = -3, "InvalidHostNameError", "Invalid host name";
+    InvalidDomainName = -4, "InvalidDomainNameError", "Invalid domain name";
+    NoNetwork = -5, "NoNetworkError", "No suitable network protocol available";
+    InvalidTtl = -6, "InvalidTTLError", "Invalid DNS TTL";
+    IsPattern = -7, "IsPatternError", "Resource record key is pattern";
+    Collision = -8, "CollisionError", "Local name collision";
+    InvalidRecord = -9, "InvalidRecordError", "Invalid record";
+    InvalidServiceName = -10, "InvalidServiceNameError", "Invalid service name";
+    InvalidServiceType = -11, "InvalidServiceTypeError", "Invalid service type";
+    InvalidPort = -12, "InvalidPortError", "Invalid port number";
+    InvalidKey = -13, "InvalidKeyError", "Invalid record key";
+    InvalidAddress = -14, "InvalidAddressError", "Invalid address";
+    Timeout = -15, "TimeoutError", "Timeout reached";
+    TooManyClients = -16, "TooManyClientsError", "Too many clients";
+    TooManyObjects = -17, "TooManyObjectsError", "Too many objects";
+    TooManyEntries = -18, "TooManyEntriesError", "Too many entries";
+    Os = -19, "OSError", "OS Error";
+    AccessDenied = -20, "AccessDenied", "Access denied";
+    InvalidOperation = -21, "InvalidOperationError", "Invalid operation";
+    DBus = -22, "DBusError", "An unexpected D-Bus error occurred";
+    Disconnected = -23, "DisconnectedError", "Daemon connection failed";
+    NoMemory = -24, "NoMemoryError", "Memory exhausted";
+    InvalidObject = -25, "InvalidObjectError", "The object passed in was not valid";
+    NoDaemon = -26, "NoDaemonError", "Daemon not running";
+    InvalidInterface = -27, "InvalidInterfaceError", "Invalid interface index";
+    InvalidProtocol = -28, "InvalidProtocolError", "Invalid protocol specification";
+    InvalidFlags = -29, "InvalidFlagsError", "Invalid flags";
+    NotFound = -30, "NotFoundError", "Not found";
+    InvalidConfig = -31, "InvalidConfigurationError", "Invalid configuration";
+    VersionMismatch = -32, "VersionMismatchError", "Version mismatch";
+    InvalidServiceSubtype = -33, "InvalidServiceSubtypeError", "Invalid service subtype";
+    InvalidPacket = -34, "InvalidPacketError", "Invalid packet";
+    InvalidDns = -35, "InvalidDNSError", "Invalid DNS return code";
+    DnsFormErr = -36, "DNSFORMERR", "DNS failure: FORMERR";
+    DnsServFail = -37, "DNSSERVFAIL", "DNS failure: SERVFAIL";
+    DnsNxDomain = -38, "DNSNXDOMAIN", "DNS failure: NXDOMAIN";
+    DnsNotImp = -39, "DNSNOTIMP", "DNS failure: NOTIMP";
+    DnsRefused = -40, "DNSREFUSED", "DNS failure: REFUSED";
+    DnsYxDomain = -41, "DNSYXDOMAIN", "DNS failure: YXDOMAIN";
+    DnsYxRrSet = -42, "DNSYXRRSET", "DNS failure: YXRRSET";
+    DnsNxRrSet = -43, "DNSNXRRSET", "DNS failure: NXRRSET";
+    DnsNotAuth = -44, "DNSNOTAUTH", "DNS failure: NOTAUTH";
+    DnsNotZone = -45, "DNSNOTZONE", "DNS failure: NOTZONE";
+    InvalidRdata = -46, "InvalidRDataError", "Invalid RDATA";
+    InvalidDnsClass = -47, "InvalidDNSClassError", "Invalid DNS class";
+    InvalidDnsType = -48, "InvalidDNSTypeError", "Invalid DNS type";
+    NotSupported = -49, "NotSupportedError", "Not supported";
+    NotPermitted = -50, "NotPermittedError", "Operation not permitted";
+    InvalidArgument = -51, "InvalidArgumentError", "Invalid argument";
+    IsEmpty = -52, "IsEmptyError", "Is empty";
+    NoChange = -53, "NoChangeError", "The requested operation is invalid because it is redundant";
+}
+
+impl AvahiError {
+    /// Parses the error name carried by `StateChanged` signals, `None` on success.
+    /// Unknown names are reported as [`AvahiError::Failure`].
+    pub fn from_state_error(error: &str) -> Option<Self> {
+        match error.strip_prefix(DBUS_PREFIX) {
+            _ if error.is_empty() => None,
+            Some("Success") => None,
+            _ => Some(Self::from_dbus_name(error).unwrap_or(Self::Failure)),
+        }
+    }
+
+    /// The avahi error returned by a method call, `None` for any other error.
+    pub fn from_zbus(error: &zbus::Error) -> Option<Self> {
+        match error {
+            zbus::Error::MethodError(name, _, _) => Self::from_dbus_name(name.as_str()),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for AvahiError {}
+
+impl From<AvahiError> for i32 {
+    fn from(value: AvahiError) -> Self {
+        value.code()
+    }
+}
+
+impl TryFrom<i32> for AvahiError {
+    type Error = i32;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        Self::from_code(value).ok_or(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AvahiError;
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            AvahiError::from_dbus_name("org.freedesktop.Avahi.CollisionError"),
+            Some(AvahiError::Collision)
+        );
+        assert_eq!(
+            AvahiError::from_dbus_name("org.freedesktop.Avahi.InvalidDNSError"),
+            Some(AvahiError::InvalidDns)
+        );
+        assert_eq!(
+            AvahiError::InvalidTtl.dbus_name(),
+            "org.freedesktop.Avahi.InvalidTTLError"
+        );
+        assert_eq!(AvahiError::from_dbus_name("CollisionError"), None);
+        assert_eq!(
+            AvahiError::from_dbus_name("org.freedesktop.Avahi.Success"),
+            None
+        );
+        assert_eq!(AvahiError::from_dbus_name(""), None);
+    }
+
+    #[test]
+    fn state_errors() {
+        assert_eq!(AvahiError::from_state_error(""), None);
+        assert_eq!(
+            AvahiError::from_state_error("org.freedesktop.Avahi.Success"),
+            None
+        );
+        assert_eq!(
+            AvahiError::from_state_error("org.freedesktop.Avahi.TimeoutError"),
+            Some(AvahiError::Timeout)
+        );
+        assert_eq!(
+            AvahiError::from_state_error("org.freedesktop.Avahi.UnknownError"),
+            Some(AvahiError::Failure)
+        );
+        assert_eq!(
+            AvahiError::from_state_error("org.example.Error"),
+            Some(AvahiError::Failure)
+        );
+    }
+
+    #[test]
+    fn codes() {
+        for code in -53..=-1 {
+            let error = AvahiError::from_code(code).unwrap();
+            assert_eq!(error.code(), code);
+            assert_eq!(AvahiError::try_from(code), Ok(error));
+            assert_eq!(AvahiError::from_dbus_name(error.dbus_name()), Some(error));
+        }
+
+        assert_eq!(AvahiError::from_code(0), None);
+        assert_eq!(AvahiError::from_code(-54), None);
+        assert_eq!(AvahiError::try_from(1), Err(1));
+        assert_eq!(AvahiError::Collision.to_string(), "Local name collision");
+    }
+}
@@ -19,7 +19,7 @@
 //! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
 use zbus::{proxy, zvariant::Optional};
 
-use crate::{InterfaceIndex, Protocol};
+use crate::{InterfaceIndex, LookupResultFlags, Protocol};
 #[proxy(
     interface = "org.freedesktop.Avahi.AddressResolver",
     default_service = "org.freedesktop.Avahi"
@@ -44,6 +44,6 @@ pub trait AddressResolver {
         aprotocol: Protocol,
         address: &str,
         name: &str,
-        flags: u32,
+        flags: LookupResultFlags,
     ) -> zbus::Result<()>;
 }
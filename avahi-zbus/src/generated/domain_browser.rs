@@ -19,7 +19,7 @@
 //! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
 use zbus::{proxy, zvariant::Optional};
 
-use crate::{InterfaceIndex, Protocol};
+use crate::{InterfaceIndex, LookupResultFlags, Protocol};
 #[proxy(
     interface = "org.freedesktop.Avahi.DomainBrowser",
     default_service = "org.freedesktop.Avahi"
@@ -50,7 +50,7 @@ pub trait DomainBrowser {
         interface: Optional<InterfaceIndex>,
         protocol: Protocol,
         domain: &str,
-        flags: u32,
+        flags: LookupResultFlags,
     ) -> zbus::Result<()>;
 
     /// ItemRemove signal
@@ -60,6 +60,6 @@ pub trait DomainBrowser {
         interface: Optional<InterfaceIndex>,
         protocol: Protocol,
         domain: &str,
-        flags: u32,
+        flags: LookupResultFlags,
     ) -> zbus::Result<()>;
 }
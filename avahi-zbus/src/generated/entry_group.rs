@@ -19,7 +19,7 @@
 //! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
 use zbus::{proxy, zvariant::Optional};
 
-use crate::{DnsClass, DnsType, EntryGroupState, InterfaceIndex, Protocol, Ttl};
+use crate::{DnsClass, DnsType, EntryGroupState, InterfaceIndex, Protocol, PublishFlags, Ttl};
 #[proxy(
     interface = "org.freedesktop.Avahi.EntryGroup",
     default_service = "org.freedesktop.Avahi"
@@ -30,7 +30,7 @@ pub trait EntryGroup {
         &self,
         interface: Optional<InterfaceIndex>,
         protocol: Protocol,
-        flags: u32,
+        flags: PublishFlags,
         name: &str,
         address: &str,
     ) -> zbus::Result<()>;
@@ -41,7 +41,7 @@ pub trait EntryGroup {
         &self,
         interface: Optional<InterfaceIndex>,
         protocol: Protocol,
-        flags: u32,
+        flags: PublishFlags,
         name: &str,
         clazz: DnsClass,
         type_: DnsType,
@@ -55,7 +55,7 @@ pub trait EntryGroup {
         &self,
         interface: Optional<InterfaceIndex>,
         protocol: Protocol,
-        flags: u32,
+        flags: PublishFlags,
         name: &str,
         type_: &str,
         domain: &str,
@@ -70,7 +70,7 @@ pub trait EntryGroup {
         &self,
         interface: Optional<InterfaceIndex>,
         protocol: Protocol,
-        flags: u32,
+        flags: PublishFlags,
         name: &str,
         type_: &str,
         domain: &str,
@@ -98,7 +98,7 @@ pub trait EntryGroup {
         &self,
         interface: Optional<InterfaceIndex>,
         protocol: Protocol,
-        flags: u32,
+        flags: PublishFlags,
         name: &str,
         type_: &str,
         domain: &str,
@@ -19,7 +19,7 @@
 //! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
 use zbus::{proxy, zvariant::Optional};
 
-use crate::{DnsClass, DnsType, InterfaceIndex, Protocol};
+use crate::{DnsClass, DnsType, InterfaceIndex, LookupResultFlags, Protocol};
 #[proxy(
     interface = "org.freedesktop.Avahi.RecordBrowser",
     default_service = "org.freedesktop.Avahi"
@@ -53,7 +53,7 @@ pub trait RecordBrowser {
         clazz: DnsClass,
         type_: DnsType,
         rdata: Vec<u8>,
-        flags: u32,
+        flags: LookupResultFlags,
     ) -> zbus::Result<()>;
 
     /// ItemRemove signal
@@ -66,6 +66,6 @@ pub trait RecordBrowser {
         clazz: DnsClass,
         type_: DnsType,
         rdata: Vec<u8>,
-        flags: u32,
+        flags: LookupResultFlags,
     ) -> zbus::Result<()>;
 }
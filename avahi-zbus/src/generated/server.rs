@@ -20,8 +20,8 @@
 use zbus::{proxy, zvariant::Optional};
 
 use crate::{
-    DnsClass, DnsType, DomainBrowserType, InterfaceIndex, Protocol, ResolveAddressResponse,
-    ResolveHostNameResponse, ResolveServiceResponse, ServerState,
+    DnsClass, DnsType, DomainBrowserType, InterfaceIndex, LookupFlags, Protocol,
+    ResolveAddressResponse, ResolveHostNameResponse, ResolveServiceResponse, ServerState,
 };
 #[proxy(
     interface = "org.freedesktop.Avahi.Server",
@@ -35,7 +35,7 @@ pub trait Server {
         interface: Optional<InterfaceIndex>,
         protocol: Protocol,
         address: &str,
-        flags: u32,
+        flags: LookupFlags,
     ) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
 
     /// DomainBrowserNew method
@@ -45,7 +45,7 @@ pub trait Server {
         protocol: Protocol,
         domain: &str,
         btype: DomainBrowserType,
-        flags: u32,
+        flags: LookupFlags,
     ) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
 
     /// EntryGroupNew method
@@ -92,7 +92,7 @@ pub trait Server {
         protocol: Protocol,
         name: &str,
         aprotocol: Protocol,
-        flags: u32,
+        flags: LookupFlags,
     ) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
 
     /// IsNSSSupportAvailable method
@@ -108,7 +108,7 @@ pub trait Server {
         name: &str,
         clazz: DnsClass,
         type_: DnsType,
-        flags: u32,
+        flags: LookupFlags,
     ) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
 
     /// ResolveAddress method
@@ -118,7 +118,7 @@ pub trait Server {
         interface: Optional<InterfaceIndex>,
         protocol: Protocol,
         address: &str,
-        flags: u32,
+        flags: LookupFlags,
     ) -> zbus::Result<ResolveAddressResponse>;
 
     /// ResolveHostName method
@@ -129,7 +129,7 @@ pub trait Server {
         protocol: Protocol,
         name: &str,
         aprotocol: Protocol,
-        flags: u32,
+        flags: LookupFlags,
     ) -> zbus::Result<ResolveHostNameResponse>;
 
     /// ResolveService method
@@ -142,7 +142,7 @@ pub trait Server {
         type_: &str,
         domain: &str,
         aprotocol: Protocol,
-        flags: u32,
+        flags: LookupFlags,
     ) -> zbus::Result<ResolveServiceResponse>;
 
     /// ServiceBrowserNew method
@@ -152,7 +152,7 @@ pub trait Server {
         protocol: Protocol,
         type_: &str,
         domain: &str,
-        flags: u32,
+        flags: LookupFlags,
     ) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
 
     /// ServiceResolverNew method
@@ -165,7 +165,7 @@ pub trait Server {
         type_: &str,
         domain: &str,
         aprotocol: Protocol,
-        flags: u32,
+        flags: LookupFlags,
     ) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
 
     /// ServiceTypeBrowserNew method
@@ -174,7 +174,7 @@ pub trait Server {
         interface: Optional<InterfaceIndex>,
         protocol: Protocol,
         domain: &str,
-        flags: u32,
+        flags: LookupFlags,
     ) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
 
     /// SetHostName method
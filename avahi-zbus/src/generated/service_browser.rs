@@ -19,7 +19,7 @@
 //! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
 use zbus::{proxy, zvariant::Optional};
 
-use crate::{InterfaceIndex, Protocol};
+use crate::{InterfaceIndex, LookupResultFlags, Protocol};
 #[proxy(
     interface = "org.freedesktop.Avahi.ServiceBrowser",
     default_service = "org.freedesktop.Avahi"
@@ -52,7 +52,7 @@ pub trait ServiceBrowser {
         name: &str,
         type_: &str,
         domain: &str,
-        flags: u32,
+        flags: LookupResultFlags,
     ) -> zbus::Result<()>;
 
     /// ItemRemove signal
@@ -64,6 +64,6 @@ pub trait ServiceBrowser {
         name: &str,
         type_: &str,
         domain: &str,
-        flags: u32,
+        flags: LookupResultFlags,
     ) -> zbus::Result<()>;
 }
@@ -19,7 +19,7 @@
 //! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
 use zbus::{proxy, zvariant::Optional};
 
-use crate::{InterfaceIndex, Protocol};
+use crate::{InterfaceIndex, LookupResultFlags, Protocol};
 #[proxy(
     interface = "org.freedesktop.Avahi.ServiceResolver",
     default_service = "org.freedesktop.Avahi"
@@ -49,6 +49,6 @@ pub trait ServiceResolver {
         address: &str,
         port: u16,
         txt: Vec<Vec<u8>>,
-        flags: u32,
+        flags: LookupResultFlags,
     ) -> zbus::Result<()>;
 }
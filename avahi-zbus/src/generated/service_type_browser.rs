@@ -19,7 +19,7 @@
 //! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
 use zbus::{proxy, zvariant::Optional};
 
-use crate::{InterfaceIndex, Protocol};
+use crate::{InterfaceIndex, LookupResultFlags, Protocol};
 #[proxy(
     interface = "org.freedesktop.Avahi.ServiceTypeBrowser",
     default_service = "org.freedesktop.Avahi"
@@ -51,7 +51,7 @@ pub trait ServiceTypeBrowser {
         protocol: Protocol,
         type_: &str,
         domain: &str,
-        flags: u32,
+        flags: LookupResultFlags,
     ) -> zbus::Result<()>;
 
     /// ItemRemove signal
@@ -62,6 +62,6 @@ pub trait ServiceTypeBrowser {
         protocol: Protocol,
         type_: &str,
         domain: &str,
-        flags: u32,
+        flags: LookupResultFlags,
     ) -> zbus::Result<()>;
 }
@@ -1,5 +1,11 @@
+mod browse;
+mod error;
 mod generated;
+mod server;
 mod types;
 
+pub use browse::*;
+pub use error::*;
 pub use generated::*;
+pub use server::*;
 pub use types::*;
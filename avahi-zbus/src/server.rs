@@ -0,0 +1,208 @@
+use zbus::{zvariant::Optional, Connection};
+
+use crate::{
+    browse::{Browse, Subscriptions},
+    AddressResolver, AddressResolverProxy, DnsClass, DnsType, DomainBrowser, DomainBrowserProxy,
+    DomainBrowserType, HostNameResolver, HostNameResolverProxy, InterfaceIndex, LookupFlags,
+    Protocol, RecordBrowser, RecordBrowserProxy, Server2Proxy, ServerProxy, ServiceBrowser,
+    ServiceBrowserProxy, ServiceResolver, ServiceResolverProxy, ServiceTypeBrowser,
+    ServiceTypeBrowserProxy,
+};
+
+/// The avahi server, independent of the D-Bus API it provides.
+///
+/// Browsers and resolvers are created through the `Server2` prepare/start calls if available,
+/// which subscribe to the signals of an object before it emits the first one.
+/// Older versions of avahi only start them right away through the `*New` calls,
+/// in that case the signals of all objects of a kind are subscribed to beforehand,
+/// once for all of them.
+#[derive(Debug, Clone)]
+pub struct Server {
+    server: ServerProxy<'static>,
+    server2: Option<Server2Proxy<'static>>,
+    api_version: u32,
+    subscriptions: Subscriptions,
+}
+
+/// Creates a browser or resolver through the `Server2` api or the matching `*New` call.
+macro_rules! browse {
+    ($self:ident, $proxy:ident, $server2:ident, $new:ident, ($($arg:expr),*)) => {{
+        if let Some(server2) = &$self.server2 {
+            return server2.$server2($($arg),*).await;
+        }
+
+        let connection = $self.server.inner().connection();
+        let subscription = $self.subscriptions.get::<$proxy>(connection).await?;
+        let creating = subscription.creating();
+        let path = $self.server.$new($($arg),*).await?;
+        let proxy = $proxy::new(connection, path).await?;
+        let signals = creating.claim(proxy.inner().path());
+        Ok(Browse::with_signals(proxy, signals))
+    }};
+}
+
+impl Server {
+    /// Connects to avahi, detecting its API version.
+    pub async fn new(connection: &Connection) -> zbus::Result<Self> {
+        let server = ServerProxy::new(connection).await?;
+        let server2 = Server2Proxy::new(connection).await?;
+
+        match server2.get_api_version().await {
+            Ok(api_version) => Ok(Self {
+                server,
+                server2: Some(server2),
+                api_version,
+                subscriptions: Subscriptions::default(),
+            }),
+            Err(zbus::Error::MethodError(name, _, _))
+                if name == "org.freedesktop.DBus.Error.UnknownInterface"
+                    || name == "org.freedesktop.DBus.Error.UnknownMethod" =>
+            {
+                let api_version = server.get_api_version().await?;
+                Ok(Self {
+                    server,
+                    server2: None,
+                    api_version,
+                    subscriptions: Subscriptions::default(),
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The version of the D-Bus API as reported by avahi.
+    pub fn api_version(&self) -> u32 {
+        self.api_version
+    }
+
+    /// Whether browsers and resolvers are created through the `Server2` API.
+    pub fn is_race_free(&self) -> bool {
+        self.server2.is_some()
+    }
+
+    /// The methods shared by both APIs, like resolving names or creating entry groups.
+    pub fn proxy(&self) -> &ServerProxy<'static> {
+        &self.server
+    }
+
+    pub async fn service_browser(
+        &self,
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        type_: &str,
+        domain: &str,
+        flags: LookupFlags,
+    ) -> zbus::Result<ServiceBrowser> {
+        browse!(
+            self,
+            ServiceBrowserProxy,
+            service_browser,
+            service_browser_new,
+            (interface, protocol, type_, domain, flags)
+        )
+    }
+
+    pub async fn service_type_browser(
+        &self,
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        domain: &str,
+        flags: LookupFlags,
+    ) -> zbus::Result<ServiceTypeBrowser> {
+        browse!(
+            self,
+            ServiceTypeBrowserProxy,
+            service_type_browser,
+            service_type_browser_new,
+            (interface, protocol, domain, flags)
+        )
+    }
+
+    pub async fn domain_browser(
+        &self,
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        domain: &str,
+        btype: DomainBrowserType,
+        flags: LookupFlags,
+    ) -> zbus::Result<DomainBrowser> {
+        browse!(
+            self,
+            DomainBrowserProxy,
+            domain_browser,
+            domain_browser_new,
+            (interface, protocol, domain, btype, flags)
+        )
+    }
+
+    pub async fn record_browser(
+        &self,
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        name: &str,
+        clazz: DnsClass,
+        type_: DnsType,
+        flags: LookupFlags,
+    ) -> zbus::Result<RecordBrowser> {
+        browse!(
+            self,
+            RecordBrowserProxy,
+            record_browser,
+            record_browser_new,
+            (interface, protocol, name, clazz, type_, flags)
+        )
+    }
+
+    pub async fn host_name_resolver(
+        &self,
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        name: &str,
+        aprotocol: Protocol,
+        flags: LookupFlags,
+    ) -> zbus::Result<HostNameResolver> {
+        browse!(
+            self,
+            HostNameResolverProxy,
+            host_name_resolver,
+            host_name_resolver_new,
+            (interface, protocol, name, aprotocol, flags)
+        )
+    }
+
+    pub async fn address_resolver(
+        &self,
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        address: &str,
+        flags: LookupFlags,
+    ) -> zbus::Result<AddressResolver> {
+        browse!(
+            self,
+            AddressResolverProxy,
+            address_resolver,
+            address_resolver_new,
+            (interface, protocol, address, flags)
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn service_resolver(
+        &self,
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        name: &str,
+        type_: &str,
+        domain: &str,
+        aprotocol: Protocol,
+        flags: LookupFlags,
+    ) -> zbus::Result<ServiceResolver> {
+        browse!(
+            self,
+            ServiceResolverProxy,
+            service_resolver,
+            service_resolver_new,
+            (interface, protocol, name, type_, domain, aprotocol, flags)
+        )
+    }
+}
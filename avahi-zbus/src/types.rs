@@ -1,6 +1,7 @@
-use std::time::Duration;
+use std::{fmt, str::FromStr, time::Duration};
 
-use serde::{Deserialize, Serialize};
+use bitflags::bitflags;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use zbus::zvariant::{NoneValue, Optional, Type};
 
@@ -14,12 +15,24 @@ pub enum Protocol {
     Inet6 = 1,
 }
 
-#[derive(
-    Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Type, Deserialize, Serialize, Hash,
-)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Type, Hash)]
 #[zvariant(signature = "i")]
 pub struct InterfaceIndex(pub u16);
 
+impl Serialize for InterfaceIndex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i32(self.0.into())
+    }
+}
+
+impl<'de> Deserialize<'de> for InterfaceIndex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let index = i32::deserialize(deserializer)?;
+        let index = index.try_into().map_err(serde::de::Error::custom)?;
+        Ok(Self(index))
+    }
+}
+
 impl NoneValue for InterfaceIndex {
     type NoneType = i32;
 
@@ -28,9 +41,12 @@ impl NoneValue for InterfaceIndex {
     }
 }
 
-impl From<i32> for InterfaceIndex {
-    fn from(value: i32) -> Self {
-        Self(value.try_into().unwrap())
+/// Fails with the value itself for negative and too large indices.
+impl TryFrom<i32> for InterfaceIndex {
+    type Error = i32;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        value.try_into().map(Self).map_err(|_| value)
     }
 }
 
@@ -58,59 +74,87 @@ pub enum EntryGroupState {
     Failure,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct PublishFlags;
-
-impl PublishFlags {
-    pub const UNIQUE: i32 = 1;
-    pub const NO_PROBE: i32 = 2;
-    pub const NO_ANNOUNCE: i32 = 4;
-    pub const ALLOW_MULTIPLE: i32 = 8;
-    pub const NO_REVERSE: i32 = 16;
-    pub const NO_COOKIE: i32 = 32;
-    pub const UPDATE: i32 = 64;
-    pub const USE_WIDE_AREA: i32 = 128;
-    pub const USE_MULTICAST: i32 = 256;
-}
-
-#[derive(Debug, Clone, Copy)]
-pub struct LookupFlags;
-
-impl LookupFlags {
-    pub const USE_WIDE_AREA: i32 = 1;
-    pub const USE_MULTICAST: i32 = 2;
-    pub const NO_TXT: i32 = 4;
-    pub const NO_ADDRESS: i32 = 8;
-}
-
-#[derive(Debug, Clone, Copy)]
-pub struct LookupResultFlags;
-
-impl LookupResultFlags {
-    pub const CACHED: i32 = 1;
-    pub const WIDE_AREA: i32 = 2;
-    pub const MULTICAST: i32 = 4;
-    pub const LOCAL: i32 = 8;
-    pub const OUR_OWN: i32 = 16;
-    pub const STATIC: i32 = 32;
-}
-
-// #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Type, Deserialize, Serialize)]
-// #[repr(i32)]
-// pub enum BrowserEvent {
-//     New,
-//     Remove,
-//     CacheExhausted,
-//     AllForNow,
-//     Failure,
-// }
-
-// #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Type, Deserialize, Serialize)]
-// #[repr(i32)]
-// pub enum ResolverEvent {
-//     Found,
-//     Failure,
-// }
+bitflags! {
+    /// Flags of the entry group methods, see `AvahiPublishFlags` of avahi.
+    ///
+    /// Human-readable formats use the names of the flags like `"UNIQUE | NO_PROBE"`.
+    #[derive(
+        Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Type, Deserialize, Serialize, Hash,
+    )]
+    #[zvariant(signature = "u")]
+    #[serde(transparent)]
+    pub struct PublishFlags: u32 {
+        /// The records are owned by this host alone.
+        const UNIQUE = 1;
+        /// Skip probing for conflicts of unique records.
+        const NO_PROBE = 2;
+        /// Do not announce the records.
+        const NO_ANNOUNCE = 4;
+        /// Allow other hosts to publish unique records of the same name.
+        const ALLOW_MULTIPLE = 8;
+        /// Do not publish the reverse PTR record of an address.
+        const NO_REVERSE = 16;
+        /// Do not mark a service as coming from this host.
+        const NO_COOKIE = 32;
+        /// Replace the records of an already committed entry group.
+        const UPDATE = 64;
+        const USE_WIDE_AREA = 128;
+        const USE_MULTICAST = 256;
+    }
+}
+
+bitflags! {
+    /// Flags of the browsers and resolvers, see `AvahiLookupFlags` of avahi.
+    #[derive(
+        Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Type, Deserialize, Serialize, Hash,
+    )]
+    #[zvariant(signature = "u")]
+    #[serde(transparent)]
+    pub struct LookupFlags: u32 {
+        const USE_WIDE_AREA = 1;
+        const USE_MULTICAST = 2;
+        /// Do not resolve the TXT record of a service.
+        const NO_TXT = 4;
+        /// Do not resolve the address of a service.
+        const NO_ADDRESS = 8;
+    }
+}
+
+bitflags! {
+    /// Flags of browsed and resolved items, see `AvahiLookupResultFlags` of avahi.
+    #[derive(
+        Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Type, Deserialize, Serialize, Hash,
+    )]
+    #[zvariant(signature = "u")]
+    #[serde(transparent)]
+    pub struct LookupResultFlags: u32 {
+        /// The item was answered from the cache.
+        const CACHED = 1;
+        const WIDE_AREA = 2;
+        const MULTICAST = 4;
+        /// The item is published by this host, in any process.
+        const LOCAL = 8;
+        /// The item is published by the same client, i.e. this D-Bus connection.
+        const OUR_OWN = 16;
+        /// The item is statically configured, e.g. in `/etc/avahi/hosts`.
+        const STATIC = 32;
+    }
+}
+
+macro_rules! flags_display {
+    ($($ty:ty),*) => {
+        $(
+            /// The names of the set flags like `A | B`, unknown bits in hex.
+            impl fmt::Display for $ty {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    bitflags::parser::to_writer(self, f)
+                }
+            }
+        )*
+    };
+}
+
+flags_display!(PublishFlags, LookupFlags, LookupResultFlags);
 
 #[derive(
     Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Type, Deserialize_repr, Serialize_repr, Hash,
@@ -125,21 +169,146 @@ pub enum DomainBrowserType {
     Max,
 }
 
-#[derive(
-    Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Type, Deserialize_repr, Serialize_repr, Hash,
-)]
-#[repr(u16)]
-pub enum DnsClass {
+/// Defines the well known values of an open dns code together with their mnemonics.
+macro_rules! dns_codes {
+    ($ty:ident, $prefix:literal, { $($(#[$doc:meta])* $name:ident = $value:literal,)* }) => {
+        impl $ty {
+            $(
+                $(#[$doc])*
+                pub const $name: Self = Self($value);
+            )*
+
+            /// Mnemonic of a well known value as used in zone files.
+            pub const fn mnemonic(&self) -> Option<&'static str> {
+                match self.0 {
+                    $($value => Some(stringify!($name)),)*
+                    _ => None,
+                }
+            }
+        }
+
+        /// Mnemonic or the generic notation of [RFC 3597](https://datatracker.ietf.org/doc/html/rfc3597#section-5).
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self.mnemonic() {
+                    Some(mnemonic) => f.write_str(mnemonic),
+                    None => write!(f, concat!($prefix, "{}"), self.0),
+                }
+            }
+        }
+
+        impl FromStr for $ty {
+            type Err = UnknownDnsCode;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let code = match s.to_ascii_uppercase().as_str() {
+                    $(stringify!($name) => return Ok(Self::$name),)*
+                    code => code.strip_prefix($prefix).and_then(|value| value.parse().ok()),
+                };
+
+                code.map(Self).ok_or_else(|| UnknownDnsCode(s.to_owned()))
+            }
+        }
+
+        impl From<u16> for $ty {
+            fn from(value: u16) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<$ty> for u16 {
+            fn from(value: $ty) -> Self {
+                value.0
+            }
+        }
+    };
+}
+
+/// Neither a known mnemonic nor in the generic `TYPE123`/`CLASS123` notation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownDnsCode(pub String);
+
+impl fmt::Display for UnknownDnsCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown dns type or class {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownDnsCode {}
+
+/// The class of a record, any value received from avahi is kept as is.
+///
+/// In mDNS the highest bit is the cache-flush bit of records
+/// and the unicast-response bit of questions,
+/// see [RFC 6762](https://datatracker.ietf.org/doc/html/rfc6762#section-10.2).
+/// Compare [`DnsClass::class`] to ignore it.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Type, Deserialize, Serialize, Hash)]
+#[serde(transparent)]
+#[zvariant(signature = "q")]
+pub struct DnsClass(pub u16);
+
+dns_codes!(DnsClass, "CLASS", {
     /// Internet (IN).
     /// This class is defined in RFC 1035 and really the only one relevant at all.
     IN = 0x01,
+    /// Chaos (CH).
+    CH = 0x03,
+    /// Hesiod (HS).
+    HS = 0x04,
+    /// Used by dynamic updates, see RFC 2136.
+    NONE = 0xFE,
+    /// Matches any class in questions.
+    ANY = 0xFF,
+});
+
+impl DnsClass {
+    const FLAG: u16 = 0x8000;
+
+    /// The class without the cache-flush or unicast-response bit.
+    pub const fn class(&self) -> Self {
+        Self(self.0 & !Self::FLAG)
+    }
+
+    /// Whether the record replaces all cached records of the same name, type and class.
+    pub const fn cache_flush(&self) -> bool {
+        self.0 & Self::FLAG != 0
+    }
+
+    /// Whether the question asks for a unicast instead of a multicast response.
+    pub const fn unicast_response(&self) -> bool {
+        self.0 & Self::FLAG != 0
+    }
+
+    pub const fn with_cache_flush(&self, cache_flush: bool) -> Self {
+        if cache_flush {
+            Self(self.0 | Self::FLAG)
+        } else {
+            self.class()
+        }
+    }
+
+    pub const fn with_unicast_response(&self, unicast_response: bool) -> Self {
+        self.with_cache_flush(unicast_response)
+    }
 }
 
-#[derive(
-    Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Type, Deserialize_repr, Serialize_repr, Hash,
-)]
-#[repr(u16)]
-pub enum DnsType {
+impl fmt::Debug for DnsClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.class())?;
+        if self.cache_flush() {
+            f.write_str("|FLUSH")?;
+        }
+        Ok(())
+    }
+}
+
+/// The type of a record, any value received from avahi is kept as is.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Type, Deserialize, Serialize, Hash)]
+#[serde(transparent)]
+#[zvariant(signature = "q")]
+pub struct DnsType(pub u16);
+
+dns_codes!(DnsType, "TYPE", {
     A = 0x01,
     NS = 0x02,
     CNAME = 0x05,
@@ -148,8 +317,98 @@ pub enum DnsType {
     HINFO = 0x0D,
     MX = 0x0F,
     TXT = 0x10,
+    RP = 0x11,
+    AFSDB = 0x12,
+    SIG = 0x18,
+    KEY = 0x19,
     AAAA = 0x1C,
+    LOC = 0x1D,
     SRV = 0x21,
+    NAPTR = 0x23,
+    KX = 0x24,
+    CERT = 0x25,
+    DNAME = 0x27,
+    /// Pseudo record of EDNS(0), e.g. sent by the mDNS known-answer suppression of Apple devices.
+    OPT = 0x29,
+    APL = 0x2A,
+    DS = 0x2B,
+    SSHFP = 0x2C,
+    IPSECKEY = 0x2D,
+    RRSIG = 0x2E,
+    /// Used by mDNS to assert that no other records of a name exist.
+    NSEC = 0x2F,
+    DNSKEY = 0x30,
+    DHCID = 0x31,
+    NSEC3 = 0x32,
+    NSEC3PARAM = 0x33,
+    TLSA = 0x34,
+    SMIMEA = 0x35,
+    HIP = 0x37,
+    CDS = 0x3B,
+    CDNSKEY = 0x3C,
+    OPENPGPKEY = 0x3D,
+    CSYNC = 0x3E,
+    ZONEMD = 0x3F,
+    SVCB = 0x40,
+    HTTPS = 0x41,
+    EUI48 = 0x6C,
+    EUI64 = 0x6D,
+    TKEY = 0xF9,
+    TSIG = 0xFA,
+    IXFR = 0xFB,
+    AXFR = 0xFC,
+    /// Matches any type in questions.
+    ANY = 0xFF,
+    URI = 0x100,
+    CAA = 0x101,
+});
+
+impl fmt::Debug for DnsType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// A service instance reported by a service browser.
+#[derive(Debug, PartialEq, Eq, Clone, Type, Deserialize, Serialize, Hash)]
+pub struct ServiceItem {
+    pub interface: Optional<InterfaceIndex>,
+    pub protocol: Protocol,
+    pub name: String,
+    pub type_: String,
+    pub domain: String,
+    pub flags: LookupResultFlags,
+}
+
+/// A service type reported by a service type browser.
+#[derive(Debug, PartialEq, Eq, Clone, Type, Deserialize, Serialize, Hash)]
+pub struct ServiceTypeItem {
+    pub interface: Optional<InterfaceIndex>,
+    pub protocol: Protocol,
+    pub type_: String,
+    pub domain: String,
+    pub flags: LookupResultFlags,
+}
+
+/// A domain reported by a domain browser.
+#[derive(Debug, PartialEq, Eq, Clone, Type, Deserialize, Serialize, Hash)]
+pub struct DomainItem {
+    pub interface: Optional<InterfaceIndex>,
+    pub protocol: Protocol,
+    pub domain: String,
+    pub flags: LookupResultFlags,
+}
+
+/// A record reported by a record browser.
+#[derive(Debug, PartialEq, Eq, Clone, Type, Deserialize, Serialize, Hash)]
+pub struct RecordItem {
+    pub interface: Optional<InterfaceIndex>,
+    pub protocol: Protocol,
+    pub name: String,
+    pub clazz: DnsClass,
+    pub type_: DnsType,
+    pub rdata: Vec<u8>,
+    pub flags: LookupResultFlags,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Type, Deserialize, Serialize, Hash)]
@@ -159,7 +418,7 @@ pub struct ResolveHostNameResponse {
     pub name: String,
     pub aprotocol: Protocol,
     pub address: String,
-    pub flags: u32,
+    pub flags: LookupResultFlags,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Type, Deserialize, Serialize, Hash)]
@@ -169,7 +428,7 @@ pub struct ResolveAddressResponse {
     pub aprotocol: Protocol,
     pub address: String,
     pub name: String,
-    pub flags: u32,
+    pub flags: LookupResultFlags,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Type, Deserialize, Serialize, Hash)]
@@ -184,10 +443,14 @@ pub struct ResolveServiceResponse {
     pub address: String,
     pub port: u16,
     pub txt: Vec<Vec<u8>>,
-    pub flags: u32,
+    pub flags: LookupResultFlags,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Type, Deserialize, Serialize, Hash)]
+/// A time-to-live in seconds.
+///
+/// Human-readable formats like toml use durations such as `75m` or `1h30m`,
+/// D-Bus carries the plain seconds.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Type, Hash)]
 pub struct Ttl(u32);
 
 const SECS_PER_MINUTE: u32 = 60;
@@ -297,3 +560,244 @@ impl Ttl {
         Duration::from_secs(self.0 as u64)
     }
 }
+
+const TTL_UNITS: [(char, u32); 4] = [
+    ('d', SECS_PER_DAY),
+    ('h', SECS_PER_HOUR),
+    ('m', SECS_PER_MINUTE),
+    ('s', 1),
+];
+
+/// Largest units first, e.g. `1h30m`, and `0s` for zero.
+impl fmt::Display for Ttl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == 0 {
+            return f.write_str("0s");
+        }
+
+        let mut rest = self.0;
+        for (unit, secs) in TTL_UNITS {
+            if rest >= secs {
+                write!(f, "{}{unit}", rest / secs)?;
+                rest %= secs;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Rejects TTLs beyond [`Ttl::CAP`].
+impl TryFrom<u32> for Ttl {
+    type Error = InvalidTtl;
+
+    fn try_from(secs: u32) -> Result<Self, Self::Error> {
+        match Self(secs) {
+            ttl if ttl > Self::CAP => Err(InvalidTtl::TooLarge(ttl)),
+            ttl => Ok(ttl),
+        }
+    }
+}
+
+/// Accepts plain seconds like `120` or numbers with the units `d`, `h`, `m` and `s`
+/// in decreasing order, e.g. `2h` or `1m30s`, up to [`Ttl::CAP`].
+impl FromStr for Ttl {
+    type Err = InvalidTtl;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidTtl::Format(s.to_owned());
+
+        if let Ok(secs) = s.parse::<u32>() {
+            return Self::try_from(secs);
+        }
+        if s.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut secs: u32 = 0;
+        let mut units = TTL_UNITS.iter();
+        let mut rest = s;
+        while !rest.is_empty() {
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .ok_or_else(invalid)?;
+            let (value, tail) = rest.split_at(end);
+            let value = value.parse::<u32>().map_err(|_| invalid())?;
+
+            let mut tail = tail.chars();
+            let unit = tail.next().ok_or_else(invalid)?;
+            // each unit at most once and larger ones first
+            let (_, unit_secs) = units.find(|(u, _)| *u == unit).ok_or_else(invalid)?;
+
+            secs = value
+                .checked_mul(*unit_secs)
+                .and_then(|value| secs.checked_add(value))
+                .ok_or_else(invalid)?;
+            rest = tail.as_str();
+        }
+
+        Self::try_from(secs)
+    }
+}
+
+impl Serialize for Ttl {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_u32(self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Ttl {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TtlVisitor;
+
+        impl serde::de::Visitor<'_> for TtlVisitor {
+            type Value = Ttl;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("seconds or a duration like 75m")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, secs: u64) -> Result<Self::Value, E> {
+                let secs = u32::try_from(secs).map_err(E::custom)?;
+                Ttl::try_from(secs).map_err(E::custom)
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, secs: i64) -> Result<Self::Value, E> {
+                let secs = u32::try_from(secs).map_err(E::custom)?;
+                Ttl::try_from(secs).map_err(E::custom)
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Self::Value, E> {
+                s.parse().map_err(E::custom)
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(TtlVisitor)
+        } else {
+            deserializer.deserialize_u32(TtlVisitor)
+        }
+    }
+}
+
+/// A TTL which cannot be parsed or exceeds [`Ttl::CAP`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidTtl {
+    /// Neither plain seconds nor a duration of the units `d`, `h`, `m` and `s`.
+    Format(String),
+    TooLarge(Ttl),
+}
+
+impl fmt::Display for InvalidTtl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Format(s) => write!(
+                f,
+                "Invalid TTL {s:?}, expected seconds or a duration like 75m"
+            ),
+            Self::TooLarge(ttl) => {
+                write!(f, "TTL {ttl} exceeds the maximum of {}", Ttl::CAP)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvalidTtl {}
+
+#[cfg(test)]
+mod tests {
+    use super::{DnsClass, DnsType, InvalidTtl, Ttl, UnknownDnsCode};
+
+    #[test]
+    fn dns_codes() {
+        assert_eq!("AAAA".parse(), Ok(DnsType::AAAA));
+        assert_eq!("srv".parse(), Ok(DnsType::SRV));
+        assert_eq!("TYPE65280".parse(), Ok(DnsType(65280)));
+        assert_eq!("type16".parse(), Ok(DnsType::TXT));
+        assert_eq!("IN".parse(), Ok(DnsClass::IN));
+        assert_eq!("CLASS32769".parse(), Ok(DnsClass(0x8001)));
+        assert_eq!(
+            "TYPE65536".parse::<DnsType>(),
+            Err(UnknownDnsCode("TYPE65536".to_owned()))
+        );
+        assert_eq!(
+            "CLASS1".parse::<DnsType>(),
+            Err(UnknownDnsCode("CLASS1".to_owned()))
+        );
+        assert!("".parse::<DnsClass>().is_err());
+
+        assert_eq!(DnsType::AAAA.to_string(), "AAAA");
+        assert_eq!(DnsType(65280).to_string(), "TYPE65280");
+        assert_eq!(DnsType(65280).mnemonic(), None);
+        assert_eq!(DnsClass(0x8001).to_string(), "CLASS32769");
+        assert_eq!(format!("{:?}", DnsClass(0x8001)), "IN|FLUSH");
+
+        assert_eq!(serde_json::to_string(&DnsType::SRV).unwrap(), "33");
+        assert_eq!(
+            serde_json::from_str::<DnsType>("65280").unwrap(),
+            DnsType(65280)
+        );
+        assert_eq!(
+            serde_json::from_str::<DnsClass>("32769").unwrap(),
+            DnsClass(0x8001)
+        );
+    }
+
+    #[test]
+    fn dns_class_flag() {
+        let class = DnsClass(0x8001);
+
+        assert_eq!(class.class(), DnsClass::IN);
+        assert!(class.cache_flush());
+        assert!(class.unicast_response());
+        assert!(!DnsClass::IN.cache_flush());
+        assert_eq!(DnsClass::IN.with_cache_flush(true), class);
+        assert_eq!(class.with_cache_flush(false), DnsClass::IN);
+        assert_eq!(DnsClass::IN.with_unicast_response(true), class);
+        assert_eq!(class.with_cache_flush(true), class);
+    }
+
+    #[test]
+    fn ttl() {
+        assert_eq!("1h30m".parse(), Ok(Ttl::from_secs(5400)));
+        assert_eq!(Ttl::from_secs(5400).to_string(), "1h30m");
+        assert_eq!("90".parse(), Ok(Ttl::from_secs(90)));
+        assert_eq!(Ttl::from_secs(90).to_string(), "1m30s");
+        assert_eq!("0".parse(), Ok(Ttl::ZERO));
+        assert_eq!(Ttl::ZERO.to_string(), "0s");
+        assert_eq!("7d".parse(), Ok(Ttl::CAP));
+        assert_eq!(Ttl::CAP.to_string(), "7d");
+
+        for invalid in ["", "2x", "1m1h", "1h1h", "h", "99999999999s", "1m-1s"] {
+            assert_eq!(
+                invalid.parse::<Ttl>(),
+                Err(InvalidTtl::Format(invalid.to_owned()))
+            );
+        }
+        assert_eq!(
+            "8d".parse::<Ttl>(),
+            Err(InvalidTtl::TooLarge(Ttl::from_days(8)))
+        );
+        assert_eq!(
+            "4294967295".parse::<Ttl>(),
+            Err(InvalidTtl::TooLarge(Ttl::MAX))
+        );
+        assert_eq!(
+            InvalidTtl::TooLarge(Ttl::from_days(8)).to_string(),
+            "TTL 8d exceeds the maximum of 7d"
+        );
+    }
+
+    #[test]
+    fn ttl_serde() {
+        assert_eq!(serde_json::to_string(&Ttl::HOUR).unwrap(), "\"1h\"");
+        assert_eq!(serde_json::from_str::<Ttl>("\"1h\"").unwrap(), Ttl::HOUR);
+        assert_eq!(serde_json::from_str::<Ttl>("60").unwrap(), Ttl::MINUTE);
+        assert!(serde_json::from_str::<Ttl>("604801").is_err());
+        assert!(serde_json::from_str::<Ttl>("-1").is_err());
+    }
+}
@@ -89,21 +89,92 @@
                   description = "The port on which to advertise the service";
                   type = lib.types.port;
                 };
+
+                txt = lib.mkOption {
+                  description = "TXT record key/value pairs, `true` publishes a boolean key";
+                  type = lib.types.attrsOf (lib.types.oneOf [lib.types.str lib.types.int lib.types.bool]);
+                  default = {};
+                };
+
+                ttl = lib.mkOption {
+                  description = "TTL of the service and its alias in seconds or as a duration like \"75m\", avahi's defaults if null";
+                  type = lib.types.nullOr (lib.types.either lib.types.ints.unsigned lib.types.nonEmptyStr);
+                  default = null;
+                };
+
+                flags = lib.mkOption {
+                  description = "Publish flags of the service and its alias";
+                  type = lib.types.listOf (lib.types.enum ["unique" "no_probe" "no_announce" "allow_multiple" "no_reverse"]);
+                  default = [];
+                };
               };
             }));
           };
+          records = lib.mkOption {
+            description = "Records published as is, each with a name, type, optional ttl and the data of its type";
+            default = [];
+            type = lib.types.listOf (lib.types.attrsOf lib.types.anything);
+          };
+          aliasMode = lib.mkOption {
+            description = "Publish aliases as a CNAME of the host or as A/AAAA records mirroring its addresses";
+            type = lib.types.enum ["cname" "addresses"];
+            default = "cname";
+          };
+          nameEncoding = lib.mkOption {
+            description = "Publish names with non-ascii labels as plain utf-8 or in their IDNA punycode form";
+            type = lib.types.enum ["utf8" "idna"];
+            default = "utf8";
+          };
           aliases = lib.mkOption {
-            description = "Valhali alias definitions";
+            description = "Valhali alias definitions, either a name or a name with an address or mode";
             default = [];
-            type = lib.types.listOf lib.types.nonEmptyStr;
+            type = lib.types.listOf (lib.types.either lib.types.nonEmptyStr (lib.types.submodule ({...}: {
+              options = {
+                name = lib.mkOption {
+                  description = "Alias name inside the .local domain";
+                  type = lib.types.nonEmptyStr;
+                };
+
+                address = lib.mkOption {
+                  description = "Address of a device which cannot publish itself";
+                  type = lib.types.nullOr lib.types.nonEmptyStr;
+                  default = null;
+                };
+
+                mode = lib.mkOption {
+                  description = "Overrides aliasMode for this alias";
+                  type = lib.types.nullOr (lib.types.enum ["cname" "addresses"]);
+                  default = null;
+                };
+
+                ttl = lib.mkOption {
+                  description = "TTL of the alias in seconds or as a duration like \"75m\"";
+                  type = lib.types.nullOr (lib.types.either lib.types.ints.unsigned lib.types.nonEmptyStr);
+                  default = null;
+                };
+
+                flags = lib.mkOption {
+                  description = "Publish flags of the alias";
+                  type = lib.types.listOf (lib.types.enum ["unique" "no_probe" "no_announce" "allow_multiple" "no_reverse"]);
+                  default = [];
+                };
+              };
+            })));
           };
         };
 
         config = lib.mkIf cfg.enable {
           environment.etc."valhali/config.toml".source = (pkgs.formats.toml {}).generate "config.toml" {
-            inherit (cfg) aliases;
+            alias_mode = cfg.aliasMode;
+            name_encoding = cfg.nameEncoding;
+            aliases = map (alias:
+              if builtins.isString alias
+              then alias
+              else lib.filterAttrs (n: v: v != null) alias)
+            cfg.aliases;
 
             services = lib.filterAttrsRecursive (n: v: v != null) cfg.services;
+            inherit (cfg) records;
           };
 
           services.avahi.enable = true;
@@ -111,9 +182,11 @@
           systemd.services.valhali = {
             description = "Valhali daemon";
             wantedBy = ["multi-user.target"];
+            reloadTriggers = [config.environment.etc."valhali/config.toml".source];
 
             serviceConfig = {
               ExecStart = "${self.packages.${pkgs.system}.valhalid}/bin/valhalid /etc/valhali/config.toml";
+              ExecReload = "${pkgs.coreutils}/bin/kill -HUP $MAINPID";
             };
           };
         };
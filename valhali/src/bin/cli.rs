@@ -1,7 +1,35 @@
-use avahi_zbus::ServerProxy;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use avahi_zbus::{
+    BrowseEvent, InterfaceIndex, LookupFlags, Protocol, ResolveHostNameResponse,
+    ResolveServiceResponse, Server, ServerProxy, ServiceItem, ServiceTypeItem,
+};
 use clap::{Parser, Subcommand};
-use valhali::status::ServerStatus;
-use zbus::Connection;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use valhali::{
+    config::{Config, Diagnostic},
+    handle::Handle,
+    interface::{Interface, Interfaces},
+    name::NameBuf,
+    server_resolve_host_name, server_resolve_service,
+    service::ServiceInstance,
+    status::ServerStatus,
+    txt::TxtEntry,
+    RESOLVE_TIMEOUT,
+};
+use zbus::{
+    export::futures_util::{future, StreamExt},
+    zvariant::Optional,
+    Connection,
+};
+
+type Error = Box<dyn std::error::Error>;
 
 #[derive(Parser)]
 struct App {
@@ -11,28 +39,411 @@ struct App {
 
 #[derive(Subcommand)]
 enum Cmd {
-    Resolve { domain: String },
-    Service { service: String },
-    Discover,
+    Resolve {
+        domain: NameBuf,
+        /// Only resolve IPv4 addresses
+        #[arg(short = '4', long, conflicts_with = "ipv6")]
+        ipv4: bool,
+        /// Only resolve IPv6 addresses
+        #[arg(short = '6', long)]
+        ipv6: bool,
+        /// Only resolve on the given network interface, by name or index
+        #[arg(short, long, default_value_t)]
+        interface: Interface,
+        /// Resolve the punycode form of a unicode name, as published with `name_encoding = "idna"`
+        #[arg(long)]
+        idna: bool,
+        /// Seconds to wait for an answer
+        #[arg(short, long, default_value_t = RESOLVE_TIMEOUT.as_secs())]
+        timeout: u64,
+    },
+    Service {
+        service: ServiceInstance,
+        /// Only resolve on the given network interface, by name or index
+        #[arg(short, long, default_value_t)]
+        interface: Interface,
+        /// Seconds to wait for an answer
+        #[arg(short, long, default_value_t = RESOLVE_TIMEOUT.as_secs())]
+        timeout: u64,
+    },
+    Discover {
+        /// Keep watching for changes instead of exiting once all cached services are listed
+        #[arg(short, long)]
+        follow: bool,
+        /// Only browse on the given network interface, by name or index
+        #[arg(short, long, default_value_t)]
+        interface: Interface,
+    },
+    /// List the network interfaces avahi can publish on
+    Interfaces,
     Status,
+    /// Validate a daemon config file or directory and report every problem
+    CheckConfig {
+        path: PathBuf,
+        /// Print the report as json
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> Result<ExitCode, Error> {
     let app = App::parse();
 
+    // does not need avahi at all, so that it can run during deployments
+    if let Cmd::CheckConfig { path, json } = &app.cmd {
+        return check_config(path, *json).await;
+    }
+
     let connection = Connection::system().await?;
     let server = ServerProxy::new(&connection).await?;
 
     match app.cmd {
-        Cmd::Resolve { domain } => todo!(),
-        Cmd::Service { service } => todo!(),
-        Cmd::Discover => todo!(),
+        Cmd::Resolve {
+            domain,
+            ipv4,
+            ipv6,
+            interface,
+            idna,
+            timeout,
+        } => {
+            let domain = if idna { domain.to_ascii()? } else { domain };
+            let aprotocols: &[Protocol] = match (ipv4, ipv6) {
+                (true, _) => &[Protocol::Inet],
+                (_, true) => &[Protocol::Inet6],
+                _ => &[Protocol::Inet, Protocol::Inet6],
+            };
+            resolve(
+                &server,
+                &domain,
+                aprotocols,
+                &interface,
+                Duration::from_secs(timeout),
+            )
+            .await?
+        }
+        Cmd::Service {
+            service,
+            interface,
+            timeout,
+        } => resolve_service(&server, &interface, &service, Duration::from_secs(timeout)).await?,
+        Cmd::Discover { follow, interface } => discover(&connection, &interface, follow).await?,
+        Cmd::Interfaces => {
+            for (InterfaceIndex(index), name) in Interface::list(&connection).await? {
+                println!("{index}\t{name}");
+            }
+        }
         Cmd::Status => {
             let status = ServerStatus::from_server(&server).await?;
             println!("{status}")
         }
+        Cmd::CheckConfig { .. } => unreachable!(),
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[derive(Debug, Serialize)]
+struct CheckReport {
+    valid: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+async fn check_config(path: &Path, json: bool) -> Result<ExitCode, Error> {
+    let report = match Config::load(path).await {
+        Ok(config) => {
+            if !json {
+                println!(
+                    "Config is valid: {} aliases, {} services, {} records",
+                    config.aliases.len(),
+                    config.services.len(),
+                    config.records.len()
+                );
+            }
+
+            CheckReport {
+                valid: true,
+                diagnostics: Vec::new(),
+            }
+        }
+        Err(e) => {
+            let diagnostics = e.diagnostics();
+            if !json {
+                for diagnostic in &diagnostics {
+                    eprintln!("{diagnostic}");
+                }
+            }
+
+            CheckReport {
+                valid: false,
+                diagnostics,
+            }
+        }
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
+
+    if report.valid {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+async fn resolve(
+    server: &ServerProxy<'_>,
+    domain: &NameBuf,
+    aprotocols: &[Protocol],
+    interface: &Interface,
+    timeout: Duration,
+) -> Result<(), Error> {
+    let interfaces = Interfaces::new(server.inner().connection());
+    let responses = future::try_join_all(aprotocols.iter().map(|aprotocol| {
+        server_resolve_host_name(server, &interfaces, interface, domain, *aprotocol, timeout)
+    }))
+    .await?;
+
+    let mut found = false;
+    for ResolveHostNameResponse {
+        interface,
+        protocol,
+        name,
+        address,
+        ..
+    } in responses.into_iter().flatten()
+    {
+        let interface = interface_name(&interfaces, interface).await?;
+        println!("{name}\t{address}\t{interface}\t{protocol:?}");
+        found = true;
+    }
+
+    if !found {
+        return Err(format!("Failed to resolve {domain}").into());
+    }
+
+    Ok(())
+}
+
+async fn resolve_service(
+    server: &ServerProxy<'_>,
+    interface: &Interface,
+    service: &ServiceInstance,
+    timeout: Duration,
+) -> Result<(), Error> {
+    let interfaces = Interfaces::new(server.inner().connection());
+    let Some(ResolveServiceResponse {
+        interface,
+        protocol,
+        name,
+        _type,
+        domain,
+        host,
+        address,
+        port,
+        txt,
+        ..
+    }) = server_resolve_service(server, &interfaces, interface, service, timeout).await?
+    else {
+        return Err(format!("Failed to resolve {service}").into());
+    };
+
+    let interface = interface_name(&interfaces, interface).await?;
+    println!("Name: {name}\nType: {_type}\nDomain: {domain}");
+    println!("Host: {host}\nAddress: {address}\nPort: {port}");
+    println!("Interface: {interface}\nProtocol: {protocol:?}\nTxt:");
+    for entry in &txt {
+        println!("  {}", TxtEntry::decode(entry));
     }
 
     Ok(())
 }
+
+async fn discover(
+    connection: &Connection,
+    interface: &Interface,
+    follow: bool,
+) -> Result<(), Error> {
+    let avahi = Server::new(connection).await?;
+    let interfaces = Interfaces::new(connection);
+    let interface = interfaces.resolve(interface).await?;
+    let mut types = avahi
+        .service_type_browser(
+            interface.clone(),
+            Protocol::Unspec,
+            "",
+            LookupFlags::empty(),
+        )
+        .await?
+        .map_proxy(Handle::new);
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    // dropped once the service type browser stopped, so that `rx` ends with the last service browser
+    let mut tx = Some(tx);
+    let mut kinds = HashSet::new();
+    // the service type browser itself and one service browser per service type,
+    // each until it listed its cache or failed
+    let mut pending = 1usize;
+    let mut types_listed = false;
+
+    println!(
+        "  {:<12} {:<8} {:<40} {:<24} Domain",
+        "Interface", "Protocol", "Name", "Type"
+    );
+
+    while follow || pending > 0 {
+        tokio::select! {
+            biased;
+            event = types.next(), if tx.is_some() => match event {
+                Some(BrowseEvent::New(item)) => {
+                    let Some(tx) = &tx else { continue };
+
+                    if kinds.insert((item.type_.clone(), item.domain.clone())) {
+                        pending += 1;
+
+                        let avahi = avahi.clone();
+                        let interface = interface.clone();
+                        let tx = tx.clone();
+                        tokio::spawn(async move {
+                            browse_services(&avahi, interface, &item, &tx).await
+                        });
+                    }
+                }
+                Some(BrowseEvent::AllForNow) if !types_listed => {
+                    types_listed = true;
+                    pending = pending.saturating_sub(1);
+                }
+                Some(BrowseEvent::Failure(error)) => return Err(error.into()),
+                Some(BrowseEvent::Error(error)) => eprintln!("Service type browser: {error}"),
+                Some(
+                    BrowseEvent::Remove(_) | BrowseEvent::AllForNow | BrowseEvent::CacheExhausted,
+                ) => (),
+                None => {
+                    eprintln!("Service type browser stopped");
+                    tx = None;
+                    if !types_listed {
+                        types_listed = true;
+                        pending = pending.saturating_sub(1);
+                    }
+                }
+            },
+            event = rx.recv() => match event {
+                Some(BrowseEvent::New(item)) => {
+                    print_service_item(&interfaces, '+', item).await?
+                }
+                Some(BrowseEvent::Remove(item)) => {
+                    print_service_item(&interfaces, '-', item).await?
+                }
+                Some(BrowseEvent::AllForNow) => pending = pending.saturating_sub(1),
+                Some(BrowseEvent::Failure(error)) => {
+                    eprintln!("Service browser failed: {error}");
+                    pending = pending.saturating_sub(1);
+                }
+                Some(BrowseEvent::Error(error)) => eprintln!("Service browser: {error}"),
+                Some(BrowseEvent::CacheExhausted) => (),
+                // the service type browser stopped and so did every service browser
+                None => break,
+            },
+        }
+    }
+
+    types.into_proxy().free().await?;
+    Ok(())
+}
+
+/// Forwards the events of a service browser to `discover`,
+/// which waits for exactly one `AllForNow` or `Failure` of every browser.
+async fn browse_services(
+    server: &Server,
+    interface: Optional<InterfaceIndex>,
+    service_type: &ServiceTypeItem,
+    tx: &mpsc::UnboundedSender<BrowseEvent<ServiceItem>>,
+) {
+    let mut listed = false;
+
+    let error = match forward_services(server, interface, service_type, tx, &mut listed).await {
+        Ok(()) if listed => return,
+        Ok(()) => "Service browser stopped".to_owned(),
+        Err(e) => e.to_string(),
+    };
+
+    let event = if listed {
+        BrowseEvent::Error(error)
+    } else {
+        BrowseEvent::Failure(error)
+    };
+    let _ = tx.send(event);
+}
+
+async fn forward_services(
+    server: &Server,
+    interface: Optional<InterfaceIndex>,
+    service_type: &ServiceTypeItem,
+    tx: &mpsc::UnboundedSender<BrowseEvent<ServiceItem>>,
+    listed: &mut bool,
+) -> Result<(), zbus::Error> {
+    let mut browser = server
+        .service_browser(
+            interface,
+            Protocol::Unspec,
+            &service_type.type_,
+            &service_type.domain,
+            LookupFlags::empty(),
+        )
+        .await?
+        .map_proxy(Handle::new);
+
+    let mut failure = None;
+    while let Some(event) = browser.next().await {
+        match event {
+            BrowseEvent::AllForNow if *listed => continue,
+            BrowseEvent::AllForNow => *listed = true,
+            // avahi emits nothing after a failure, unlike after dropped signals
+            BrowseEvent::Failure(error) => {
+                failure = Some(error);
+                break;
+            }
+            _ => (),
+        }
+
+        if tx.send(event).is_err() {
+            // `discover` is done and no longer waits for this browser
+            *listed = true;
+            break;
+        }
+    }
+
+    browser.into_proxy().free().await?;
+    match failure {
+        Some(error) => Err(zbus::Error::Failure(error)),
+        None => Ok(()),
+    }
+}
+
+async fn print_service_item(
+    interfaces: &Interfaces,
+    action: char,
+    ServiceItem {
+        interface,
+        protocol,
+        name,
+        type_,
+        domain,
+        ..
+    }: ServiceItem,
+) -> Result<(), Error> {
+    let interface = interface_name(interfaces, interface).await?;
+    let protocol = format!("{protocol:?}");
+
+    println!("{action} {interface:<12} {protocol:<8} {name:<40} {type_:<24} {domain}");
+    Ok(())
+}
+
+async fn interface_name(
+    interfaces: &Interfaces,
+    interface: Optional<InterfaceIndex>,
+) -> Result<String, zbus::Error> {
+    let name = interfaces.name(&Interface::from(interface)).await?;
+    Ok(name.unwrap_or_else(|| "*".to_owned()))
+}
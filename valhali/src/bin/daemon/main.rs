@@ -0,0 +1,181 @@
+mod reconcile;
+mod watch;
+
+use avahi_zbus::{Server, ServerState};
+use clap::Parser;
+use reconcile::Reconciler;
+use std::{collections::HashSet, path::PathBuf, process, str::FromStr, time::Duration};
+use tokio::{
+    io,
+    signal::unix::{signal, SignalKind},
+    sync, time,
+};
+use tracing::{debug, error, info, warn};
+use valhali::{
+    config::Config,
+    interface::{Interface, Interfaces},
+    name::NameBuf,
+    rdata::Cname,
+    server_browse_host_addresses, server_event_handler,
+};
+use watch::WatchMode;
+use zbus::Connection;
+
+#[derive(Parser)]
+struct App {
+    /// Config file or directory of `*.toml` fragments
+    config: PathBuf,
+    /// How changes of the config file are detected
+    #[arg(long, value_enum, default_value_t = WatchMode::Inotify)]
+    watch: WatchMode,
+    /// Seconds between reads of the config file when polling
+    #[arg(long, default_value_t = 10)]
+    poll_interval: u64,
+}
+
+/// Interval between attempts to publish entries whose names were taken or which failed.
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .try_init()?;
+
+    let app = App::parse();
+
+    let config = match Config::load(&app.config).await {
+        Ok(config) => config,
+        Err(e) => {
+            for diagnostic in e.diagnostics() {
+                error!("Config: {diagnostic}");
+            }
+            process::exit(1);
+        }
+    };
+    let (tx, mut rx) = sync::watch::channel(config);
+    rx.mark_changed();
+    let interval = Duration::from_secs(app.poll_interval);
+    let path = app.config.clone();
+    let sender = tx.clone();
+    tokio::spawn(async move {
+        if let Err(e) = watch::hangup(path, sender).await {
+            error!("SIGHUP handler: {e}");
+        }
+    });
+    match app.watch {
+        WatchMode::Inotify => {
+            tokio::spawn(async move {
+                if let Err(e) = watch::inotify(app.config.clone(), tx.clone()).await {
+                    error!("Config file watcher: {e}, falling back to polling");
+                    watch::poll(app.config, interval, tx).await;
+                }
+            });
+        }
+        WatchMode::Poll => {
+            tokio::spawn(watch::poll(app.config, interval, tx));
+        }
+    }
+    info!("Created config file watcher and SIGHUP handler");
+
+    let connection = Connection::system().await?;
+    let avahi = Server::new(&connection).await?;
+    let server = avahi.proxy().clone();
+    info!(
+        "Established connection to avahi dbus, API version {:#x}",
+        avahi.api_version()
+    );
+    if !avahi.is_race_free() {
+        warn!("Avahi lacks the Server2 API, browsing through the legacy calls");
+    }
+
+    server_event_handler(&server, |state, error| {
+        let error = error.map(|e| format!(": {e}")).unwrap_or_default();
+        match state {
+            ServerState::Failure | ServerState::Invalid => error!("Server {state:?}{error}"),
+            ServerState::Collision => warn!("Server {state:?}{error}"),
+            ServerState::Registering | ServerState::Running => info!("Server {state:?}{error}"),
+        }
+    })
+    .await;
+    info!("Created server signals handler");
+
+    let host_name = NameBuf::from_str(&server.get_host_name_fqdn().await?)?;
+    let (sender, mut addresses) = sync::watch::channel(HashSet::new());
+    let browser = server_browse_host_addresses(
+        &avahi,
+        &Interfaces::new(&connection),
+        &Interface::Any,
+        &host_name,
+        sender,
+    )
+    .await?;
+    tokio::spawn(async move {
+        if let Ok(Err(e)) = browser.await {
+            error!("Host address browser: {e}");
+        }
+    });
+    info!("Created host address browser");
+
+    let mut reconciler = Reconciler::new(server, Cname::from(host_name));
+    let mut interval = time::interval(Duration::from_secs(1));
+    let mut retry = time::interval_at(time::Instant::now() + RETRY_INTERVAL, RETRY_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        tokio::select! {
+            _ = wait_for_shutdown() => {
+                info!("Shutting down");
+                reconciler.clear().await?;
+                break;
+            }
+            _ = rx.changed() => {
+                let config = rx.borrow_and_update().clone();
+                let reconciled = reconciler.reconcile(&config).await;
+                if logged("Reconciling", reconciled)? {
+                    info!("Reconciled entry groups");
+                }
+            }
+            _ = retry.tick() => {
+                logged("Retrying skipped entries", reconciler.retry().await)?;
+            }
+            Ok(()) = addresses.changed() => {
+                let addresses = addresses.borrow_and_update().clone();
+                let updated = reconciler.update_addresses(addresses).await;
+                logged("Updating host addresses", updated)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Logs an error of the reconciler, whether it succeeded.
+/// Failed entries are retried by the reconciler, only a lost connection to avahi stops the daemon.
+fn logged(action: &str, result: Result<(), zbus::Error>) -> Result<bool, zbus::Error> {
+    match result {
+        Ok(()) => Ok(true),
+        Err(e) if reconcile::connection_lost(&e) => Err(e),
+        Err(e) => {
+            error!("{action}: {e}");
+            Ok(false)
+        }
+    }
+}
+
+async fn wait_for_shutdown() -> io::Result<()> {
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigquit = signal(SignalKind::quit())?;
+
+    tokio::select! {
+        _ = sigint.recv() => debug!("Received SIGINT"),
+        _ = sigterm.recv() => debug!("Received SIGTERM"),
+        _ = sigquit.recv() => debug!("Received SIGQUIT"),
+    }
+
+    Ok(())
+}
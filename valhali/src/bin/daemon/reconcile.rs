@@ -0,0 +1,638 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt,
+    hash::Hash,
+    net::IpAddr,
+};
+
+use avahi_zbus::{AvahiError, EntryGroupProxy, EntryGroupState, PublishFlags, ServerProxy, Ttl};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+use valhali::{
+    config::{AliasConfig, AliasMode, AliasTarget, Config, RecordConfig, RecordDataConfig},
+    entry_group_add_address, entry_group_add_record, entry_group_add_service,
+    entry_group_add_service_records, entry_group_event_handler, entry_group_update_service_txt,
+    handle::Handle,
+    host::HostAddress,
+    interface::{Interface, Interfaces},
+    name::{Name, NameBuf, NameEncoding, NameError},
+    rdata::{Aaaa, Cname, Ptr, Srv, A},
+    record::Record,
+    server_resolve_name,
+    service::Service,
+    RESOLVE_TIMEOUT,
+};
+
+/// An entry group owning exactly one alias or service.
+struct Entry {
+    group: Handle<EntryGroupProxy<'static>>,
+    handler: AbortOnDrop<Result<(), zbus::Error>>,
+}
+
+impl Entry {
+    async fn new(server: &ServerProxy<'static>, name: String) -> Result<Self, zbus::Error> {
+        let path = server.entry_group_new().await?;
+        let group = Handle::new(EntryGroupProxy::new(server.inner().connection(), path).await?);
+
+        let handler = entry_group_event_handler(&group, move |state, error| {
+            let error = error.map(|e| format!(": {e}")).unwrap_or_default();
+            match state {
+                EntryGroupState::Failure => error!("EntryGroup {name} {state:?}{error}"),
+                EntryGroupState::Collision => warn!("EntryGroup {name} {state:?}{error}"),
+                EntryGroupState::Established
+                | EntryGroupState::Registering
+                | EntryGroupState::Uncommitted => info!("EntryGroup {name} {state:?}{error}"),
+            }
+        })
+        .await;
+
+        Ok(Self {
+            group,
+            handler: AbortOnDrop(handler),
+        })
+    }
+
+    async fn free(self) -> Result<(), zbus::Error> {
+        let Self { group, handler } = self;
+        drop(handler);
+        group.free().await
+    }
+
+    /// Frees the group, a failure is only logged unless the connection to avahi was lost.
+    async fn withdraw(self, kind: &str, entry: impl fmt::Display) -> Result<(), zbus::Error> {
+        match self.free().await {
+            Ok(()) => {
+                info!("Withdrew {kind}: {entry}");
+                Ok(())
+            }
+            Err(e) if connection_lost(&e) => Err(e),
+            Err(e) => {
+                error!("{kind} {entry} not withdrawn: {e}");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Stops the event handler of an entry however it is dropped, like its [`Handle`] frees the group.
+struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Whether an entry of the config is published.
+enum State {
+    Published(Entry),
+    /// Kept so that it is not checked on every reconciliation, see [`Reconciler::retry`].
+    Skipped(Skip),
+}
+
+/// Why an entry is not published.
+#[derive(Debug, PartialEq, Eq)]
+enum Skip {
+    /// The name already resolves to the target, e.g. as published by avahi itself.
+    Published,
+    /// The name belongs to another host.
+    Taken { address: String, host: String },
+    /// The addresses of this host are not known yet,
+    /// so it is unclear whether the name already resolves to one of them.
+    NoAddresses,
+    /// A name cannot be encoded, which validated configs already report.
+    Unencodable {
+        encoding: NameEncoding,
+        error: NameError,
+    },
+    /// Avahi rejected the entry itself, e.g. because of a name collision.
+    Rejected(AvahiError),
+    /// Publishing failed for another reason, e.g. as avahi had no network yet.
+    Failed(String),
+}
+
+impl Skip {
+    fn log(&self, kind: &str, entry: impl fmt::Display) {
+        match self {
+            Self::Published => info!("{kind} {entry} already published"),
+            Self::Taken { address, host } => {
+                error!("{kind} {entry} already resolves to {address} ({host})")
+            }
+            Self::NoAddresses => debug!("{kind} {entry} waits for the addresses of this host"),
+            Self::Unencodable { encoding, error } => {
+                error!("{kind} {entry} cannot be encoded as {encoding}: {error}")
+            }
+            Self::Rejected(error) => error!("{kind} {entry} not published: {error}"),
+            Self::Failed(error) => error!("{kind} {entry} failed: {error}"),
+        }
+    }
+}
+
+/// Logs why an entry was skipped, unless it was skipped for the same reason before.
+fn log_skip(kind: &str, entry: impl fmt::Display, previous: Option<&State>, state: &State) {
+    if let State::Skipped(skip) = state {
+        match previous {
+            Some(State::Skipped(previous)) if previous == skip => (),
+            _ => skip.log(kind, entry),
+        }
+    }
+}
+
+/// Stores the state of an entry, logging why it was skipped unless that did not change.
+fn track<K>(entries: &mut HashMap<K, State>, kind: &str, key: K, state: State)
+where
+    K: Eq + Hash + fmt::Display,
+{
+    log_skip(kind, &key, entries.get(&key), &state);
+    entries.insert(key, state);
+}
+
+/// Keeps one entry group per alias, service and record,
+/// so that changing one of them does not withdraw all the others.
+pub struct Reconciler {
+    server: ServerProxy<'static>,
+    interfaces: Interfaces,
+    cname: Cname,
+    /// Encoding of the published names, changing it republishes everything.
+    encoding: NameEncoding,
+    /// Current addresses of this host, mirrored by aliases in address mode.
+    addresses: HashSet<HostAddress>,
+    aliases: HashMap<AliasConfig, State>,
+    services: HashMap<String, (Service, State)>,
+    records: HashMap<RecordConfig, State>,
+}
+
+impl Reconciler {
+    pub fn new(server: ServerProxy<'static>, cname: Cname) -> Self {
+        Self {
+            interfaces: Interfaces::new(server.inner().connection()),
+            server,
+            cname,
+            encoding: NameEncoding::default(),
+            addresses: HashSet::new(),
+            aliases: HashMap::new(),
+            services: HashMap::new(),
+            records: HashMap::new(),
+        }
+    }
+
+    pub async fn reconcile(&mut self, config: &Config) -> Result<(), zbus::Error> {
+        if config.name_encoding != self.encoding {
+            self.clear().await?;
+            self.encoding = config.name_encoding;
+        }
+
+        let aliases = config.all_aliases().collect::<BTreeSet<_>>();
+
+        let stale = self
+            .aliases
+            .keys()
+            .filter(|alias| !aliases.contains(*alias))
+            .cloned()
+            .collect::<Vec<_>>();
+        for alias in stale {
+            if let Some(State::Published(entry)) = self.aliases.remove(&alias) {
+                entry.withdraw("Entry", &alias).await?;
+            }
+        }
+
+        for alias in aliases {
+            if !self.aliases.contains_key(&alias) {
+                self.publish_alias(alias).await?;
+            }
+        }
+
+        let stale = self
+            .services
+            .keys()
+            .filter(|name| !config.services.contains_key(*name))
+            .cloned()
+            .collect::<Vec<_>>();
+        for name in stale {
+            if let Some((service, State::Published(entry))) = self.services.remove(&name) {
+                entry.withdraw("Service", &service).await?;
+            }
+        }
+
+        for (name, service) in &config.services {
+            let service = service.to_service(name);
+
+            match self.services.remove(name) {
+                Some((old, state)) if old == service => {
+                    self.services.insert(name.clone(), (old, state));
+                }
+                // avahi would reset the TTL of a TXT record published by hand
+                Some((old, State::Published(entry)))
+                    if service.ttl.is_none()
+                        && Service {
+                            txt: service.txt.clone(),
+                            ..old.clone()
+                        } == service =>
+                {
+                    match entry_group_update_service_txt(
+                        &entry.group,
+                        &self.interfaces,
+                        &Interface::Any,
+                        &service,
+                    )
+                    .await
+                    {
+                        Ok(()) => {
+                            info!("Updated Service TXT: {service}");
+                            let state = State::Published(entry);
+                            self.services.insert(name.clone(), (service, state));
+                        }
+                        Err(e) => {
+                            warn!("Updating TXT of {name} failed, republishing: {e}");
+                            entry.withdraw("Service", &service).await?;
+                            self.publish_service(service).await?;
+                        }
+                    }
+                }
+                Some((old, State::Published(entry))) => {
+                    entry.withdraw("Service", &old).await?;
+                    self.publish_service(service).await?;
+                }
+                Some((_, State::Skipped(_))) | None => self.publish_service(service).await?,
+            }
+        }
+
+        let stale = self
+            .records
+            .keys()
+            .filter(|record| !config.records.contains(record))
+            .cloned()
+            .collect::<Vec<_>>();
+        for record in stale {
+            if let Some(State::Published(entry)) = self.records.remove(&record) {
+                entry.withdraw("Record", &record).await?;
+            }
+        }
+
+        for record in &config.records {
+            if !self.records.contains_key(record) {
+                self.publish_record(record.clone()).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Republishes every alias in address mode with the new addresses of this host,
+    /// skipped ones are checked again as their names may resolve to one of them.
+    pub async fn update_addresses(
+        &mut self,
+        addresses: HashSet<HostAddress>,
+    ) -> Result<(), zbus::Error> {
+        if addresses == self.addresses {
+            return Ok(());
+        }
+        self.addresses = addresses;
+
+        let mut failures = Vec::new();
+        for (alias, state) in &self.aliases {
+            let State::Published(entry) = state else {
+                continue;
+            };
+            if alias.target != AliasTarget::Host(AliasMode::Addresses) {
+                continue;
+            }
+
+            // only aliases which could be encoded have an entry
+            let Ok(name) = alias.name.encode(self.encoding) else {
+                continue;
+            };
+
+            // avahi cannot withdraw single records of a committed entry group
+            let updated = async {
+                entry.group.reset().await?;
+                publish_addresses(&entry.group, &name, &self.addresses, alias.ttl, alias.flags)
+                    .await
+            };
+            match updated.await {
+                Ok(()) => info!("Updated Entry: {alias}"),
+                Err(e) => failures.push((alias.clone(), e)),
+            }
+        }
+
+        // dropping the entries frees their groups, they are published again on retry
+        for (alias, e) in failures {
+            let state = failed(e)?;
+            track(&mut self.aliases, "Entry", alias, state);
+        }
+
+        for alias in skipped(&self.aliases) {
+            if alias.target == AliasTarget::Host(AliasMode::Addresses) {
+                self.publish_alias(alias).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Publishes the aliases, services and records which were skipped before,
+    /// e.g. once their names are free.
+    pub async fn retry(&mut self) -> Result<(), zbus::Error> {
+        for alias in skipped(&self.aliases) {
+            self.publish_alias(alias).await?;
+        }
+        let services = self
+            .services
+            .values()
+            .filter(|(_, state)| matches!(state, State::Skipped(_)))
+            .map(|(service, _)| service.clone())
+            .collect::<Vec<_>>();
+        for service in services {
+            self.publish_service(service).await?;
+        }
+        for record in skipped(&self.records) {
+            self.publish_record(record).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Withdraws every alias and service.
+    pub async fn clear(&mut self) -> Result<(), zbus::Error> {
+        for (alias, state) in self.aliases.drain() {
+            if let State::Published(entry) = state {
+                entry.withdraw("Entry", &alias).await?;
+            }
+        }
+        for (_, (service, state)) in self.services.drain() {
+            if let State::Published(entry) = state {
+                entry.withdraw("Service", &service).await?;
+            }
+        }
+        for (record, state) in self.records.drain() {
+            if let State::Published(entry) = state {
+                entry.withdraw("Record", &record).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Publishes an alias or keeps track of why it was skipped,
+    /// which is only logged if the reason changed.
+    async fn publish_alias(&mut self, alias: AliasConfig) -> Result<(), zbus::Error> {
+        let state = match self.add_alias(&alias).await {
+            Ok(state) => state,
+            Err(e) => failed(e)?,
+        };
+        track(&mut self.aliases, "Entry", alias, state);
+
+        Ok(())
+    }
+
+    async fn add_alias(&self, alias: &AliasConfig) -> Result<State, zbus::Error> {
+        let AliasConfig {
+            name,
+            target,
+            ttl,
+            flags,
+        } = alias;
+        let name = match name.encode(self.encoding) {
+            Ok(name) => name,
+            Err(error) => {
+                return Ok(State::Skipped(Skip::Unencodable {
+                    encoding: self.encoding,
+                    error,
+                }))
+            }
+        };
+
+        if *target == AliasTarget::Host(AliasMode::Addresses) && self.addresses.is_empty() {
+            return Ok(State::Skipped(Skip::NoAddresses));
+        }
+
+        if let Some(response) = server_resolve_name(&self.server, &name, RESOLVE_TIMEOUT).await? {
+            let owned = match target {
+                AliasTarget::Host(AliasMode::Cname) => response.name == self.cname.to_string(),
+                AliasTarget::Host(AliasMode::Addresses) => self
+                    .addresses
+                    .iter()
+                    .any(|host| host.address.to_string() == response.address),
+                AliasTarget::Address(address) => response.address == address.to_string(),
+            };
+            let skip = if owned {
+                Skip::Published
+            } else {
+                Skip::Taken {
+                    address: response.address,
+                    host: response.name,
+                }
+            };
+            return Ok(State::Skipped(skip));
+        }
+
+        let entry = Entry::new(&self.server, name.to_string()).await?;
+        let record_ttl = ttl.unwrap_or(AliasConfig::DEFAULT_TTL);
+        match target {
+            AliasTarget::Host(AliasMode::Cname) => {
+                let record = Record::new(name.clone(), record_ttl, &self.cname).with_flags(*flags);
+                entry_group_add_record(&entry.group, &self.interfaces, &Interface::Any, &record)
+                    .await?;
+                entry.group.commit().await?;
+            }
+            AliasTarget::Host(AliasMode::Addresses) => {
+                publish_addresses(&entry.group, &name, &self.addresses, *ttl, *flags).await?;
+            }
+            AliasTarget::Address(IpAddr::V4(address)) => {
+                let record =
+                    Record::new(name.clone(), record_ttl, A::from(*address)).with_flags(*flags);
+                entry_group_add_record(&entry.group, &self.interfaces, &Interface::Any, &record)
+                    .await?;
+                entry.group.commit().await?;
+            }
+            AliasTarget::Address(IpAddr::V6(address)) => {
+                let record =
+                    Record::new(name.clone(), record_ttl, Aaaa::from(*address)).with_flags(*flags);
+                entry_group_add_record(&entry.group, &self.interfaces, &Interface::Any, &record)
+                    .await?;
+                entry.group.commit().await?;
+            }
+        }
+        info!("Published Entry: {alias}");
+
+        Ok(State::Published(entry))
+    }
+
+    /// Publishes a service or keeps track of why it was skipped, like [`Self::publish_alias`].
+    async fn publish_service(&mut self, service: Service) -> Result<(), zbus::Error> {
+        let state = match self.add_service(&service).await {
+            Ok(state) => state,
+            Err(e) => failed(e)?,
+        };
+
+        let previous = self
+            .services
+            .get(&service.name)
+            .filter(|(old, _)| *old == service)
+            .map(|(_, state)| state);
+        log_skip("Service", &service, previous, &state);
+        self.services.insert(service.name.clone(), (service, state));
+
+        Ok(())
+    }
+
+    async fn add_service(&self, service: &Service) -> Result<State, zbus::Error> {
+        let entry = Entry::new(&self.server, service.name.clone()).await?;
+        match service.ttl {
+            Some(ttl) => {
+                entry_group_add_service_records(
+                    &entry.group,
+                    &self.interfaces,
+                    &Interface::Any,
+                    service,
+                    self.cname.as_name(),
+                    ttl,
+                )
+                .await?
+            }
+            None => {
+                entry_group_add_service(&entry.group, &self.interfaces, &Interface::Any, service)
+                    .await?
+            }
+        }
+        entry.group.commit().await?;
+        info!("Published Service: {service}");
+
+        Ok(State::Published(entry))
+    }
+
+    /// Publishes a record or keeps track of why it was skipped, like [`Self::publish_alias`].
+    async fn publish_record(&mut self, record: RecordConfig) -> Result<(), zbus::Error> {
+        let state = match self.add_record(&record).await {
+            Ok(state) => state,
+            Err(e) => failed(e)?,
+        };
+        track(&mut self.records, "Record", record, state);
+
+        Ok(())
+    }
+
+    async fn add_record(&self, record: &RecordConfig) -> Result<State, zbus::Error> {
+        let (name, data) = match encode_record(record, self.encoding) {
+            Ok(encoded) => encoded,
+            Err(error) => {
+                return Ok(State::Skipped(Skip::Unencodable {
+                    encoding: self.encoding,
+                    error,
+                }))
+            }
+        };
+        let RecordConfig { ttl, flags, .. } = record;
+
+        let entry = Entry::new(&self.server, name.to_string()).await?;
+        match &data {
+            RecordDataConfig::Txt { txt } => {
+                let txt = Record::new(name.clone(), *ttl, txt.clone()).with_flags(*flags);
+                entry_group_add_record(&entry.group, &self.interfaces, &Interface::Any, &txt)
+                    .await?;
+            }
+            RecordDataConfig::Srv(srv) => {
+                let srv = Record::new(name.clone(), *ttl, srv.clone()).with_flags(*flags);
+                entry_group_add_record(&entry.group, &self.interfaces, &Interface::Any, &srv)
+                    .await?;
+            }
+            RecordDataConfig::Ptr { target } => {
+                let ptr = Record::new(name.clone(), *ttl, Ptr(target.clone())).with_flags(*flags);
+                entry_group_add_record(&entry.group, &self.interfaces, &Interface::Any, &ptr)
+                    .await?;
+            }
+            RecordDataConfig::Hinfo(hinfo) => {
+                let hinfo = Record::new(name.clone(), *ttl, hinfo.clone()).with_flags(*flags);
+                entry_group_add_record(&entry.group, &self.interfaces, &Interface::Any, &hinfo)
+                    .await?;
+            }
+        }
+        entry.group.commit().await?;
+        info!("Published Record: {record}");
+
+        Ok(State::Published(entry))
+    }
+}
+
+/// The entries which were skipped, to publish them again.
+fn skipped<K: Clone>(entries: &HashMap<K, State>) -> Vec<K> {
+    entries
+        .iter()
+        .filter(|(_, state)| matches!(state, State::Skipped(_)))
+        .map(|(key, _)| key.clone())
+        .collect()
+}
+
+/// Skips entries which could not be published, so that a single entry cannot stop the daemon,
+/// telling apart the ones avahi rejected itself, like on a name collision or an invalid name.
+/// Only a lost connection to avahi is passed on.
+fn failed(e: zbus::Error) -> Result<State, zbus::Error> {
+    if connection_lost(&e) {
+        return Err(e);
+    }
+
+    let skip = match AvahiError::from_zbus(&e) {
+        Some(
+            error @ (AvahiError::Collision
+            | AvahiError::IsPattern
+            | AvahiError::InvalidHostName
+            | AvahiError::InvalidDomainName
+            | AvahiError::InvalidTtl
+            | AvahiError::InvalidRecord
+            | AvahiError::InvalidServiceName
+            | AvahiError::InvalidServiceType
+            | AvahiError::InvalidServiceSubtype
+            | AvahiError::InvalidPort
+            | AvahiError::InvalidKey
+            | AvahiError::InvalidAddress
+            | AvahiError::InvalidRdata
+            | AvahiError::InvalidFlags
+            | AvahiError::NotPermitted),
+        ) => Skip::Rejected(error),
+        _ => Skip::Failed(e.to_string()),
+    };
+    Ok(State::Skipped(skip))
+}
+
+/// Whether the connection to the bus is gone, after which nothing can be published anymore.
+pub fn connection_lost(e: &zbus::Error) -> bool {
+    matches!(e, zbus::Error::InputOutput(_))
+}
+
+/// The owner name and data of a record with every name in the given encoding.
+fn encode_record(
+    record: &RecordConfig,
+    encoding: NameEncoding,
+) -> Result<(NameBuf, RecordDataConfig), NameError> {
+    let data = match &record.data {
+        RecordDataConfig::Srv(srv) => RecordDataConfig::Srv(Srv {
+            target: srv.target.encode(encoding)?,
+            ..srv.clone()
+        }),
+        RecordDataConfig::Ptr { target } => RecordDataConfig::Ptr {
+            target: target.encode(encoding)?,
+        },
+        data => data.clone(),
+    };
+
+    Ok((record.name.encode(encoding)?, data))
+}
+
+/// Adds every address to the group and commits it,
+/// an empty group stays uncommitted until the addresses are known.
+async fn publish_addresses(
+    group: &EntryGroupProxy<'_>,
+    name: &Name,
+    addresses: &HashSet<HostAddress>,
+    ttl: Option<Ttl>,
+    flags: PublishFlags,
+) -> Result<(), zbus::Error> {
+    if addresses.is_empty() {
+        return Ok(());
+    }
+
+    for address in addresses {
+        entry_group_add_address(group, name, address, ttl, flags).await?;
+    }
+    group.commit().await
+}
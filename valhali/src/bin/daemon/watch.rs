@@ -0,0 +1,210 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::{OsStr, OsString},
+    io,
+    path::{self, Path, PathBuf},
+    time::Duration,
+};
+
+use clap::ValueEnum;
+use inotify::{Inotify, WatchDescriptor, WatchMask, Watches};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+    time,
+};
+use tracing::{debug, error};
+use valhali::config::Config;
+use zbus::export::futures_util::StreamExt;
+
+/// Quiet period after the last file event before the config is read again,
+/// as editors and deployments usually touch the file several times in a row.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Upper bound of symlinks followed, mirroring `MAXSYMLINKS` on linux.
+const MAX_LINKS: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum WatchMode {
+    /// Reload as soon as the config file changes
+    Inotify,
+    /// Periodically re-read the config file
+    Poll,
+}
+
+pub async fn poll(path: PathBuf, interval: Duration, tx: watch::Sender<Config>) {
+    let mut interval = time::interval(interval);
+
+    loop {
+        interval.tick().await;
+        reload(&path, &tx).await;
+    }
+}
+
+/// Watches the parent directory of the config file and of every symlink leading to it,
+/// so that atomic renames and symlink swaps (e.g. NixOS `environment.etc`) are noticed.
+/// Config directories are watched themselves and every fragment like a single config file.
+pub async fn inotify(path: PathBuf, tx: watch::Sender<Config>) -> io::Result<()> {
+    let path = path::absolute(path)?;
+
+    let mut stream = Inotify::init()?.into_event_stream([0; 4096])?;
+    let mut watches = stream.watches();
+    let mut targets = rewatch(&mut watches, &path, HashMap::new())?;
+
+    while let Some(event) = stream.next().await {
+        let event = event?;
+
+        match (targets.get(&event.wd), &event.name) {
+            (Some(filter), Some(name)) if filter.matches(name) => (),
+            _ => continue,
+        }
+
+        while let Ok(Some(event)) = time::timeout(DEBOUNCE, stream.next()).await {
+            event?;
+        }
+
+        targets = rewatch(&mut watches, &path, targets)?;
+        reload(&path, &tx).await;
+    }
+
+    Ok(())
+}
+
+/// Re-reads the config on every SIGHUP and forces a reconciliation,
+/// even if the config itself did not change.
+pub async fn hangup(path: PathBuf, tx: watch::Sender<Config>) -> io::Result<()> {
+    let mut sighup = signal(SignalKind::hangup())?;
+
+    while sighup.recv().await.is_some() {
+        debug!("Received SIGHUP");
+
+        match Config::load(&path).await {
+            Ok(config) => {
+                tx.send_replace(config);
+            }
+            Err(e) => {
+                for diagnostic in e.diagnostics() {
+                    error!("Config: {diagnostic}");
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn reload(path: &Path, tx: &watch::Sender<Config>) {
+    match Config::load(path).await {
+        Ok(loaded) => {
+            tx.send_if_modified(|config| {
+                if *config != loaded {
+                    *config = loaded;
+                    debug!("Config changed");
+                    true
+                } else {
+                    false
+                }
+            });
+        }
+        Err(e) => {
+            for diagnostic in e.diagnostics() {
+                error!("Config: {diagnostic}");
+            }
+        }
+    }
+}
+
+type Targets = HashMap<WatchDescriptor, Filter>;
+
+#[derive(Debug, Default)]
+struct Filter {
+    names: HashSet<OsString>,
+    fragments: bool,
+}
+
+impl Filter {
+    fn matches(&self, name: &OsStr) -> bool {
+        self.names.contains(name)
+            || self.fragments && Path::new(name).extension().is_some_and(|ext| ext == "toml")
+    }
+}
+
+fn rewatch(watches: &mut Watches, path: &Path, old: Targets) -> io::Result<Targets> {
+    for wd in old.into_keys() {
+        // the directory might be gone already, which removes the watch as well
+        let _ = watches.remove(wd);
+    }
+
+    let mask = WatchMask::CREATE
+        | WatchMask::CLOSE_WRITE
+        | WatchMask::MOVED_TO
+        | WatchMask::MOVED_FROM
+        | WatchMask::DELETE
+        | WatchMask::ATTRIB;
+
+    let mut targets = Targets::new();
+    let mut chain = link_chain(path);
+
+    if path.is_dir() {
+        match watches.add(path, mask) {
+            Ok(wd) => targets.entry(wd).or_default().fragments = true,
+            Err(e) => error!("Watching {}: {e}", path.display()),
+        }
+
+        for entry in std::fs::read_dir(path)? {
+            let fragment = entry?.path();
+            if fragment.extension().is_some_and(|ext| ext == "toml") {
+                chain.extend(link_chain(&fragment));
+            }
+        }
+    }
+
+    for (dir, name) in chain {
+        match watches.add(&dir, mask) {
+            Ok(wd) => {
+                targets.entry(wd).or_default().names.insert(name);
+            }
+            Err(e) => error!("Watching {}: {e}", dir.display()),
+        }
+    }
+
+    if targets.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No directory to watch for {}", path.display()),
+        ));
+    }
+
+    Ok(targets)
+}
+
+/// Collects the parent directory and file name of the path,
+/// every symlink it resolves through and every symlinked ancestor directory.
+fn link_chain(path: &Path) -> Vec<(PathBuf, OsString)> {
+    let mut chain = Vec::new();
+    let mut current = path.to_path_buf();
+
+    for _ in 0..MAX_LINKS {
+        for ancestor in current.ancestors() {
+            if ancestor != current && !ancestor.is_symlink() {
+                continue;
+            }
+
+            if let (Some(parent), Some(name)) = (ancestor.parent(), ancestor.file_name()) {
+                chain.push((parent.to_path_buf(), name.to_owned()));
+            }
+        }
+
+        match std::fs::read_link(&current) {
+            Ok(target) => {
+                current = match current.parent() {
+                    Some(parent) => parent.join(target),
+                    None => target,
+                };
+            }
+            Err(_) => break,
+        }
+    }
+
+    chain
+}
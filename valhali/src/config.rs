@@ -0,0 +1,1404 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    net::IpAddr,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use avahi_zbus::{PublishFlags, Ttl};
+use serde::{ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+use tokio::{fs, io};
+use toml::Spanned;
+
+use crate::{
+    name::{NameBuf, NameEncoding},
+    rdata::{Hinfo, Srv},
+    service::{Service, ServiceKind, TransportProtocol},
+    txt::TxtRecord,
+};
+
+/// Service instance names are published as a single DNS label.
+const MAX_LABEL_LEN: usize = 63;
+
+/// Publish flags which entries may set, by their name in the config.
+const ENTRY_FLAGS: [(&str, PublishFlags); 5] = [
+    ("unique", PublishFlags::UNIQUE),
+    ("no_probe", PublishFlags::NO_PROBE),
+    ("no_announce", PublishFlags::NO_ANNOUNCE),
+    ("allow_multiple", PublishFlags::ALLOW_MULTIPLE),
+    ("no_reverse", PublishFlags::NO_REVERSE),
+];
+
+/// Flags which avahi accepts for single records, including services published with a TTL.
+const RECORD_FLAGS: PublishFlags = PublishFlags::UNIQUE
+    .union(PublishFlags::NO_PROBE)
+    .union(PublishFlags::NO_ANNOUNCE)
+    .union(PublishFlags::ALLOW_MULTIPLE);
+
+/// Flags which avahi accepts for addresses, which are always unique.
+const ADDRESS_FLAGS: PublishFlags = PublishFlags::NO_PROBE
+    .union(PublishFlags::NO_ANNOUNCE)
+    .union(PublishFlags::NO_REVERSE);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "ConfigRepr", into = "ConfigRepr")]
+pub struct Config {
+    /// Mode of aliases without an address or mode of their own and of service aliases.
+    pub alias_mode: AliasMode,
+    /// Encoding of every published name with non-ascii labels.
+    pub name_encoding: NameEncoding,
+    pub aliases: Vec<AliasConfig>,
+    pub services: HashMap<String, ServiceConfig>,
+    pub records: Vec<RecordConfig>,
+}
+
+impl Config {
+    /// Loads a single config file or merges every `*.toml` fragment of a directory
+    /// in lexical order of their file names.
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+
+        let metadata = fs::metadata(path)
+            .await
+            .map_err(|e| ConfigError::Io(path.to_owned(), e))?;
+        let files = if metadata.is_dir() {
+            fragments(path).await?
+        } else {
+            vec![path.to_owned()]
+        };
+
+        let mut validator = Validator::default();
+        for file in files {
+            let contents = fs::read_to_string(&file)
+                .await
+                .map_err(|e| ConfigError::Io(file.clone(), e))?;
+            validator.fragment(&file, &contents);
+        }
+
+        validator.finish()
+    }
+
+    /// Parses the contents of a single config file,
+    /// the path is only used to report diagnostics.
+    pub fn parse(path: impl AsRef<Path>, contents: &str) -> Result<Self, ConfigError> {
+        let mut validator = Validator::default();
+        validator.fragment(path.as_ref(), contents);
+        validator.finish()
+    }
+
+    /// Every alias including the ones of services.
+    pub fn all_aliases(&self) -> impl Iterator<Item = AliasConfig> + '_ {
+        let services = self.services.values().filter_map(|service| {
+            service.alias.clone().map(|name| AliasConfig {
+                name,
+                target: AliasTarget::Host(self.alias_mode),
+                ttl: service.ttl,
+                flags: PublishFlags::empty(),
+            })
+        });
+
+        self.aliases.iter().cloned().chain(services)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ConfigRepr {
+    #[serde(default)]
+    alias_mode: AliasMode,
+    #[serde(default)]
+    name_encoding: NameEncoding,
+    #[serde(default)]
+    aliases: Vec<AliasRepr>,
+    #[serde(default)]
+    services: HashMap<String, ServiceConfig>,
+    #[serde(default)]
+    records: Vec<RecordConfig>,
+}
+
+impl From<ConfigRepr> for Config {
+    fn from(repr: ConfigRepr) -> Self {
+        let aliases = repr
+            .aliases
+            .into_iter()
+            .map(|alias| alias.into_config(repr.alias_mode))
+            .collect();
+
+        Self {
+            alias_mode: repr.alias_mode,
+            name_encoding: repr.name_encoding,
+            aliases,
+            services: repr.services,
+            records: repr.records,
+        }
+    }
+}
+
+impl From<Config> for ConfigRepr {
+    fn from(config: Config) -> Self {
+        let aliases = config
+            .aliases
+            .into_iter()
+            .map(|alias| AliasRepr::new(alias, config.alias_mode))
+            .collect();
+
+        Self {
+            alias_mode: config.alias_mode,
+            name_encoding: config.name_encoding,
+            aliases,
+            services: config.services,
+            records: config.records,
+        }
+    }
+}
+
+/// Lists the `*.toml` files of a config directory sorted by name.
+pub async fn fragments(path: &Path) -> Result<Vec<PathBuf>, ConfigError> {
+    let io_error = |e| ConfigError::Io(path.to_owned(), e);
+
+    let mut fragments = Vec::new();
+    let mut entries = fs::read_dir(path).await.map_err(io_error)?;
+    while let Some(entry) = entries.next_entry().await.map_err(io_error)? {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            fragments.push(path);
+        }
+    }
+    fragments.sort();
+
+    Ok(fragments)
+}
+
+/// How an alias of this host is published.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum AliasMode {
+    /// A CNAME record pointing at the host name.
+    #[default]
+    Cname,
+    /// A and AAAA records mirroring the current addresses of the host,
+    /// as several mDNS resolvers do not follow CNAMEs.
+    Addresses,
+}
+
+impl FromStr for AliasMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cname" => Ok(Self::Cname),
+            "addresses" => Ok(Self::Addresses),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AliasTarget {
+    /// An alias of this host.
+    Host(AliasMode),
+    /// A name for a device which cannot publish itself.
+    Address(IpAddr),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "AliasRepr", into = "AliasRepr")]
+pub struct AliasConfig {
+    pub name: NameBuf,
+    pub target: AliasTarget,
+    /// TTL of the published records, avahi's default for host names if unset.
+    pub ttl: Option<Ttl>,
+    pub flags: PublishFlags,
+}
+
+impl AliasConfig {
+    /// TTL of the records published by valhali itself rather than avahi.
+    pub const DEFAULT_TTL: Ttl = Ttl::MINUTE;
+
+    /// The flags avahi accepts for the alias, depending on whether it adds addresses or records.
+    fn allowed_flags(&self) -> (PublishFlags, &'static str) {
+        match (self.target, self.ttl) {
+            (AliasTarget::Host(AliasMode::Addresses), None) => (ADDRESS_FLAGS, "host addresses"),
+            _ => (RECORD_FLAGS, "records"),
+        }
+    }
+}
+
+impl fmt::Display for AliasConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.target {
+            AliasTarget::Host(AliasMode::Cname) => write!(f, "{}", self.name)?,
+            AliasTarget::Host(AliasMode::Addresses) => {
+                write!(f, "{} -> host addresses", self.name)?
+            }
+            AliasTarget::Address(address) => write!(f, "{} -> {address}", self.name)?,
+        }
+        if let Some(ttl) = self.ttl {
+            write!(f, " (TTL {ttl})")?;
+        }
+        if !self.flags.is_empty() {
+            write!(f, " ({})", self.flags)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum AliasRepr {
+    Name(NameBuf),
+    Table {
+        name: NameBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        address: Option<IpAddr>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mode: Option<AliasMode>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ttl: Option<Ttl>,
+        #[serde(
+            default,
+            skip_serializing_if = "PublishFlags::is_empty",
+            serialize_with = "serialize_flags",
+            deserialize_with = "deserialize_flags"
+        )]
+        flags: PublishFlags,
+    },
+}
+
+impl AliasRepr {
+    /// Omits the mode of an alias if it is the given alias mode of the config.
+    fn new(
+        AliasConfig {
+            name,
+            target,
+            ttl,
+            flags,
+        }: AliasConfig,
+        alias_mode: AliasMode,
+    ) -> Self {
+        match target {
+            AliasTarget::Host(mode) if mode == alias_mode && ttl.is_none() && flags.is_empty() => {
+                Self::Name(name)
+            }
+            AliasTarget::Host(mode) => Self::Table {
+                name,
+                address: None,
+                mode: (mode != alias_mode).then_some(mode),
+                ttl,
+                flags,
+            },
+            AliasTarget::Address(address) => Self::Table {
+                name,
+                address: Some(address),
+                mode: None,
+                ttl,
+                flags,
+            },
+        }
+    }
+
+    /// Aliases without an address or mode of their own follow the alias mode of the config.
+    fn into_config(self, alias_mode: AliasMode) -> AliasConfig {
+        let (name, target, ttl, flags) = match self {
+            Self::Name(name) => (
+                name,
+                AliasTarget::Host(alias_mode),
+                None,
+                PublishFlags::empty(),
+            ),
+            Self::Table {
+                name,
+                address: Some(address),
+                ttl,
+                flags,
+                ..
+            } => (name, AliasTarget::Address(address), ttl, flags),
+            Self::Table {
+                name,
+                mode,
+                ttl,
+                flags,
+                ..
+            } => (
+                name,
+                AliasTarget::Host(mode.unwrap_or(alias_mode)),
+                ttl,
+                flags,
+            ),
+        };
+
+        AliasConfig {
+            name,
+            target,
+            ttl,
+            flags,
+        }
+    }
+}
+
+impl From<AliasRepr> for AliasConfig {
+    fn from(repr: AliasRepr) -> Self {
+        repr.into_config(AliasMode::default())
+    }
+}
+
+impl From<AliasConfig> for AliasRepr {
+    fn from(alias: AliasConfig) -> Self {
+        Self::new(alias, AliasMode::default())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ServiceConfig {
+    pub alias: Option<NameBuf>,
+    pub kind: ServiceKind,
+    pub protocol: TransportProtocol,
+    pub port: u16,
+    #[serde(default)]
+    pub txt: TxtRecord,
+    /// TTL of the service records and its alias, avahi's defaults if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<Ttl>,
+    /// Publish flags of the service records, only accepted by avahi if published with a TTL.
+    #[serde(
+        default,
+        skip_serializing_if = "PublishFlags::is_empty",
+        serialize_with = "serialize_flags",
+        deserialize_with = "deserialize_flags"
+    )]
+    pub flags: PublishFlags,
+}
+
+impl ServiceConfig {
+    pub fn to_service(&self, name: &str) -> Service {
+        Service::new(name.to_owned(), self.kind.clone(), self.protocol, self.port)
+            .with_txt(self.txt.clone())
+            .with_ttl(self.ttl)
+            .with_flags(self.flags)
+    }
+}
+
+/// A hand-crafted record published as is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RecordConfig {
+    pub name: NameBuf,
+    #[serde(default = "default_ttl")]
+    pub ttl: Ttl,
+    #[serde(
+        default,
+        skip_serializing_if = "PublishFlags::is_empty",
+        serialize_with = "serialize_flags",
+        deserialize_with = "deserialize_flags"
+    )]
+    pub flags: PublishFlags,
+    #[serde(flatten)]
+    pub data: RecordDataConfig,
+}
+
+impl fmt::Display for RecordConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            name, ttl, data, ..
+        } = self;
+
+        match data {
+            RecordDataConfig::Txt { txt } => write!(f, "{name} {} TXT {txt}", ttl.as_secs()),
+            RecordDataConfig::Srv(srv) => write!(f, "{name} {} SRV {srv}", ttl.as_secs()),
+            RecordDataConfig::Ptr { target } => write!(f, "{name} {} PTR {target}", ttl.as_secs()),
+            RecordDataConfig::Hinfo(hinfo) => write!(f, "{name} {} HINFO {hinfo}", ttl.as_secs()),
+        }
+    }
+}
+
+fn default_ttl() -> Ttl {
+    Ttl::DEFAULT
+}
+
+/// Reads a TTL given in seconds or as a duration like `"75m"`, at most [`Ttl::CAP`].
+fn parse_ttl(value: toml::Value) -> Result<Ttl, String> {
+    let ttl = match value {
+        toml::Value::Integer(secs) => match u32::try_from(secs) {
+            Ok(secs) => Ttl::try_from(secs),
+            Err(_) => return Err(format!("Invalid TTL {secs}")),
+        },
+        toml::Value::String(ttl) => Ttl::from_str(&ttl),
+        _ => return Err("TTL must be seconds or a duration like \"75m\"".to_owned()),
+    };
+
+    ttl.map_err(|e| e.to_string())
+}
+
+/// Reads a list of publish flags like `["no_probe", "allow_multiple"]`.
+fn parse_flags(value: toml::Value) -> Result<PublishFlags, String> {
+    let toml::Value::Array(names) = value else {
+        return Err("Flags must be a list like [\"no_probe\"]".to_owned());
+    };
+
+    names
+        .into_iter()
+        .try_fold(PublishFlags::empty(), |flags, name| {
+            let (_, flag) = ENTRY_FLAGS
+                .iter()
+                .find(|(flag, _)| name.as_str() == Some(flag))
+                .ok_or_else(|| {
+                    let expected = ENTRY_FLAGS.map(|(flag, _)| flag).join(", ");
+                    format!("Unknown flag {name}, expected one of {expected}")
+                })?;
+            Ok(flags | *flag)
+        })
+}
+
+/// Writes flags as a list like `["no_probe"]`, the way [`parse_flags`] reads them.
+fn serialize_flags<S: Serializer>(flags: &PublishFlags, serializer: S) -> Result<S::Ok, S::Error> {
+    let other = flags.difference(ENTRY_FLAGS.iter().map(|(_, flag)| *flag).collect());
+    if !other.is_empty() {
+        return Err(S::Error::custom(format!(
+            "Flags {other} cannot be set in a config"
+        )));
+    }
+
+    let names = ENTRY_FLAGS
+        .iter()
+        .filter(|(_, flag)| flags.contains(*flag))
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+    names.serialize(serializer)
+}
+
+fn deserialize_flags<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PublishFlags, D::Error> {
+    let value = toml::Value::deserialize(deserializer)?;
+    parse_flags(value).map_err(serde::de::Error::custom)
+}
+
+/// Rejects the flags avahi does not accept for the kind of entry published.
+fn check_flags(flags: PublishFlags, allowed: PublishFlags, entries: &str) -> Result<(), String> {
+    let rejected = ENTRY_FLAGS
+        .iter()
+        .filter(|(_, flag)| flags.contains(*flag) && !allowed.contains(*flag))
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+
+    match rejected.as_slice() {
+        [] => Ok(()),
+        [flag] => Err(format!("Flag {flag} cannot be set on {entries}")),
+        flags => Err(format!(
+            "Flags {} cannot be set on {entries}",
+            flags.join(", ")
+        )),
+    }
+}
+
+/// Typed record data selected by the `type` key of a record.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "UPPERCASE", deny_unknown_fields)]
+pub enum RecordDataConfig {
+    Txt {
+        #[serde(default)]
+        txt: TxtRecord,
+    },
+    Srv(Srv),
+    Ptr {
+        target: NameBuf,
+    },
+    Hinfo(Hinfo),
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("{}: {1}", .0.display())]
+    Io(PathBuf, #[source] io::Error),
+    #[error("{}", Diagnostics(.0))]
+    Invalid(Vec<Diagnostic>),
+}
+
+impl ConfigError {
+    /// Every problem found, io errors are reported without a location.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            Self::Io(file, e) => vec![Diagnostic {
+                file: file.clone(),
+                location: None,
+                message: e.to_string(),
+            }],
+            Self::Invalid(diagnostics) => diagnostics.clone(),
+        }
+    }
+}
+
+struct Diagnostics<'a>(&'a [Diagnostic]);
+
+impl fmt::Display for Diagnostics<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut iter = self.0.iter();
+
+        if let Some(first) = iter.next() {
+            write!(f, "{first}")?;
+        }
+        for diagnostic in iter {
+            write!(f, "\n{diagnostic}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A single problem of a config file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub location: Option<Location>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+
+        if let Some(Location { line, column, .. }) = &self.location {
+            write!(f, ":{line}:{column}")?;
+        }
+
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Position inside a config file, lines and columns start at one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+    /// Byte offsets into the file.
+    pub span: Range<usize>,
+}
+
+impl Location {
+    fn new(contents: &str, span: Range<usize>) -> Self {
+        let before = &contents[..span.start.min(contents.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            span,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawConfig {
+    alias_mode: Option<Spanned<String>>,
+    name_encoding: Option<Spanned<String>>,
+    #[serde(default)]
+    aliases: Vec<Spanned<toml::Value>>,
+    #[serde(default)]
+    services: BTreeMap<Spanned<String>, RawServiceConfig>,
+    #[serde(default)]
+    records: Vec<Spanned<toml::Table>>,
+}
+
+#[derive(Deserialize)]
+struct RawServiceConfig {
+    alias: Option<Spanned<String>>,
+    kind: Spanned<String>,
+    protocol: Spanned<String>,
+    port: Spanned<i64>,
+    txt: Option<Spanned<toml::Table>>,
+    ttl: Option<Spanned<toml::Value>>,
+    flags: Option<Spanned<toml::Value>>,
+}
+
+struct Source<'a> {
+    file: &'a Path,
+    contents: &'a str,
+}
+
+impl Source<'_> {
+    fn diagnostic(&self, span: Option<Range<usize>>, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            file: self.file.to_owned(),
+            location: span.map(|span| Location::new(self.contents, span)),
+            message: message.into(),
+        }
+    }
+
+    fn position(&self, span: Range<usize>) -> String {
+        let Location { line, column, .. } = Location::new(self.contents, span);
+        format!("{}:{line}:{column}", self.file.display())
+    }
+}
+
+/// Collects every problem of all fragments instead of stopping at the first one.
+#[derive(Default)]
+struct Validator {
+    config: Config,
+    alias_mode: Option<String>,
+    name_encoding: Option<String>,
+    /// Converted and checked once the alias mode is known, which any fragment may define.
+    alias_reprs: Vec<(AliasRepr, Diagnostic)>,
+    aliases: HashMap<NameBuf, String>,
+    services: HashMap<String, String>,
+    /// Published names, checked once the name encoding is known.
+    names: Vec<(NameBuf, Diagnostic)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator {
+    fn fragment(&mut self, file: &Path, contents: &str) {
+        let source = Source { file, contents };
+
+        let raw = match toml::from_str::<RawConfig>(contents) {
+            Ok(raw) => raw,
+            Err(e) => {
+                let diagnostic = source.diagnostic(e.span(), e.message());
+                self.diagnostics.push(diagnostic);
+                return;
+            }
+        };
+
+        if let Some(mode) = raw.alias_mode {
+            self.alias_mode(&source, mode);
+        }
+        if let Some(encoding) = raw.name_encoding {
+            self.name_encoding(&source, encoding);
+        }
+
+        for alias in raw.aliases {
+            let diagnostic = source.diagnostic(Some(alias.span()), "");
+            if let Some(alias) = self.alias_repr(&source, alias) {
+                self.alias_reprs.push((alias, diagnostic));
+            }
+        }
+
+        for (name, service) in raw.services {
+            self.service(&source, name, service);
+        }
+
+        for record in raw.records {
+            if let Some(record) = self.record(&source, record) {
+                self.config.records.push(record);
+            }
+        }
+    }
+
+    fn finish(mut self) -> Result<Config, ConfigError> {
+        let alias_mode = self.config.alias_mode;
+        for (alias, mut diagnostic) in self.alias_reprs {
+            let alias = alias.into_config(alias_mode);
+            let (allowed, entries) = alias.allowed_flags();
+            match check_flags(alias.flags, allowed, entries) {
+                Ok(()) => self.config.aliases.push(alias),
+                Err(e) => {
+                    diagnostic.message = format!("Invalid alias {}: {e}", alias.name);
+                    self.diagnostics.push(diagnostic);
+                }
+            }
+        }
+
+        let encoding = self.config.name_encoding;
+        for (name, mut diagnostic) in self.names {
+            if let Err(e) = name.encode(encoding) {
+                diagnostic.message = format!("{name} cannot be encoded as {encoding}: {e}");
+                self.diagnostics.push(diagnostic);
+            }
+        }
+
+        if self.diagnostics.is_empty() {
+            return Ok(self.config);
+        }
+
+        self.diagnostics.sort_by_key(|diagnostic| {
+            let start = diagnostic.location.as_ref().map(|l| l.span.start);
+            (diagnostic.file.clone(), start)
+        });
+        Err(ConfigError::Invalid(self.diagnostics))
+    }
+
+    fn alias_mode(&mut self, source: &Source, mode: Spanned<String>) {
+        let span = mode.span();
+
+        if let Some(first) = &self.alias_mode {
+            let message = format!("Alias mode is already defined at {first}");
+            self.diagnostics
+                .push(source.diagnostic(Some(span), message));
+            return;
+        }
+
+        match AliasMode::from_str(mode.get_ref()) {
+            Ok(mode) => self.config.alias_mode = mode,
+            Err(mode) => {
+                let message = format!("Invalid alias mode {mode:?}, expected cname or addresses");
+                self.diagnostics
+                    .push(source.diagnostic(Some(span.clone()), message));
+            }
+        }
+        self.alias_mode = Some(source.position(span));
+    }
+
+    fn name_encoding(&mut self, source: &Source, encoding: Spanned<String>) {
+        let span = encoding.span();
+
+        if let Some(first) = &self.name_encoding {
+            let message = format!("Name encoding is already defined at {first}");
+            self.diagnostics
+                .push(source.diagnostic(Some(span), message));
+            return;
+        }
+
+        match NameEncoding::from_str(encoding.get_ref()) {
+            Ok(encoding) => self.config.name_encoding = encoding,
+            Err(encoding) => {
+                let message = format!("Invalid name encoding {encoding:?}, expected utf8 or idna");
+                self.diagnostics
+                    .push(source.diagnostic(Some(span.clone()), message));
+            }
+        }
+        self.name_encoding = Some(source.position(span));
+    }
+
+    fn alias_repr(&mut self, source: &Source, alias: Spanned<toml::Value>) -> Option<AliasRepr> {
+        let span = alias.span();
+        let mut report = |message: String| {
+            self.diagnostics
+                .push(source.diagnostic(Some(span.clone()), message));
+            None
+        };
+
+        match alias.into_inner() {
+            toml::Value::String(name) => {
+                let name = self.alias(source, Spanned::new(span, name))?;
+                Some(AliasRepr::Name(name))
+            }
+            toml::Value::Table(mut table) => {
+                let name = match table.remove("name") {
+                    Some(toml::Value::String(name)) => name,
+                    _ => return report("Alias requires a name".to_owned()),
+                };
+                let address = match table.remove("address") {
+                    Some(toml::Value::String(address)) => match IpAddr::from_str(&address) {
+                        Ok(address) => Some(address),
+                        Err(e) => return report(format!("Invalid address {address:?}: {e}")),
+                    },
+                    Some(_) => return report("Alias address must be a string".to_owned()),
+                    None => None,
+                };
+                let mode = match table.remove("mode") {
+                    Some(toml::Value::String(mode)) => match AliasMode::from_str(&mode) {
+                        Ok(mode) => Some(mode),
+                        Err(mode) => {
+                            return report(format!(
+                                "Invalid alias mode {mode:?}, expected cname or addresses"
+                            ))
+                        }
+                    },
+                    Some(_) => return report("Alias mode must be a string".to_owned()),
+                    None => None,
+                };
+                let ttl = match table.remove("ttl").map(parse_ttl) {
+                    Some(Ok(ttl)) => Some(ttl),
+                    Some(Err(e)) => return report(e),
+                    None => None,
+                };
+                let flags = match table.remove("flags").map(parse_flags) {
+                    Some(Ok(flags)) => flags,
+                    Some(Err(e)) => return report(e),
+                    None => PublishFlags::empty(),
+                };
+                if let Some(key) = table.keys().next() {
+                    return report(format!("Unknown alias key {key:?}"));
+                }
+
+                if address.is_some() && mode.is_some() {
+                    return report("Alias cannot have both an address and a mode".to_owned());
+                }
+
+                let name = self.alias(source, Spanned::new(span, name))?;
+                Some(AliasRepr::Table {
+                    name,
+                    address,
+                    mode,
+                    ttl,
+                    flags,
+                })
+            }
+            _ => report("Alias must be a name or a table of name and address".to_owned()),
+        }
+    }
+
+    fn alias(&mut self, source: &Source, alias: Spanned<String>) -> Option<NameBuf> {
+        let span = alias.span();
+
+        let name = match NameBuf::from_str(alias.get_ref()) {
+            Ok(name) => name,
+            Err(e) => {
+                let message = format!("Invalid alias {:?}: {e}", alias.get_ref());
+                self.diagnostics
+                    .push(source.diagnostic(Some(span), message));
+                return None;
+            }
+        };
+
+        let is_local = name
+            .iter()
+            .last()
+            .is_some_and(|label| label.as_str().eq_ignore_ascii_case("local"));
+        if !is_local {
+            let message = format!("Alias {name} is outside of the .local domain");
+            self.diagnostics
+                .push(source.diagnostic(Some(span), message));
+            return None;
+        }
+
+        if let Some(first) = self.aliases.get(&name) {
+            let message = format!("Alias {name} is already defined at {first}");
+            self.diagnostics
+                .push(source.diagnostic(Some(span), message));
+            return None;
+        }
+
+        self.aliases
+            .insert(name.clone(), source.position(span.clone()));
+        self.names
+            .push((name.clone(), source.diagnostic(Some(span), "")));
+        Some(name)
+    }
+
+    fn record(&mut self, source: &Source, record: Spanned<toml::Table>) -> Option<RecordConfig> {
+        let span = record.span();
+        let mut report = |message: String| {
+            self.diagnostics
+                .push(source.diagnostic(Some(span.clone()), message));
+            None
+        };
+
+        let mut table = record.into_inner();
+        let name = match table.remove("name") {
+            Some(toml::Value::String(name)) => match NameBuf::from_str(&name) {
+                Ok(name) => name,
+                Err(e) => return report(format!("Invalid record name {name:?}: {e}")),
+            },
+            _ => return report("Record requires a name".to_owned()),
+        };
+        let ttl = match table.remove("ttl").map(parse_ttl) {
+            Some(Ok(ttl)) => ttl,
+            Some(Err(e)) => return report(format!("Invalid record {name}: {e}")),
+            None => default_ttl(),
+        };
+        let flags = match table.remove("flags").map(parse_flags) {
+            Some(Ok(flags)) => flags,
+            Some(Err(e)) => return report(format!("Invalid record {name}: {e}")),
+            None => PublishFlags::empty(),
+        };
+        if let Err(e) = check_flags(flags, RECORD_FLAGS, "records") {
+            return report(format!("Invalid record {name}: {e}"));
+        }
+        let data = match RecordDataConfig::deserialize(toml::Value::Table(table)) {
+            Ok(data) => data,
+            Err(e) => return report(format!("Invalid record {name}: {}", e.message())),
+        };
+
+        let target = match &data {
+            RecordDataConfig::Srv(srv) => Some(&srv.target),
+            RecordDataConfig::Ptr { target } => Some(target),
+            RecordDataConfig::Txt { .. } | RecordDataConfig::Hinfo(_) => None,
+        };
+        for name in [Some(&name), target].into_iter().flatten() {
+            self.names
+                .push((name.clone(), source.diagnostic(Some(span.clone()), "")));
+        }
+
+        Some(RecordConfig {
+            name,
+            ttl,
+            flags,
+            data,
+        })
+    }
+
+    fn service(&mut self, source: &Source, name: Spanned<String>, raw: RawServiceConfig) {
+        let mut valid = true;
+        let mut report = |diagnostics: &mut Vec<Diagnostic>, span, message: String| {
+            diagnostics.push(source.diagnostic(Some(span), message));
+            valid = false;
+        };
+
+        let name_span = name.span();
+        let name = name.into_inner();
+        if name.is_empty() || name.len() > MAX_LABEL_LEN {
+            let message =
+                format!("Service name {name:?} must have between 1 and {MAX_LABEL_LEN} bytes");
+            report(&mut self.diagnostics, name_span.clone(), message);
+        }
+        if let Some(first) = self.services.get(&name) {
+            let message = format!("Service {name} is already defined at {first}");
+            report(&mut self.diagnostics, name_span.clone(), message);
+        }
+
+        let kind = ServiceKind::from_str(raw.kind.get_ref())
+            .map_err(|e| {
+                let message = format!("Invalid service kind {:?}: {e}", raw.kind.get_ref());
+                report(&mut self.diagnostics, raw.kind.span(), message)
+            })
+            .ok();
+
+        let protocol = TransportProtocol::from_str(raw.protocol.get_ref())
+            .map_err(|e| {
+                let message = format!("Invalid transport protocol {e:?}, expected tcp or udp");
+                report(&mut self.diagnostics, raw.protocol.span(), message)
+            })
+            .ok();
+
+        let port = match u16::try_from(*raw.port.get_ref()) {
+            Ok(port) if port != 0 => Some(port),
+            _ => {
+                let message = format!("Port {} is not between 1 and 65535", raw.port.get_ref());
+                report(&mut self.diagnostics, raw.port.span(), message);
+                None
+            }
+        };
+
+        let txt = match raw.txt {
+            Some(txt) => {
+                let span = txt.span();
+                TxtRecord::deserialize(toml::Value::Table(txt.into_inner()))
+                    .map_err(|e| {
+                        let message = format!("Invalid TXT record: {}", e.message());
+                        report(&mut self.diagnostics, span, message)
+                    })
+                    .ok()
+            }
+            None => Some(TxtRecord::new()),
+        };
+
+        let ttl = match raw.ttl {
+            Some(value) => {
+                let span = value.span();
+                parse_ttl(value.into_inner())
+                    .map(Some)
+                    .map_err(|e| report(&mut self.diagnostics, span, e))
+                    .ok()
+            }
+            None => Some(None),
+        };
+
+        let flags = match raw.flags {
+            Some(value) => {
+                let span = value.span();
+                // avahi publishes whole services only with the default flags
+                let (allowed, entries) = match ttl {
+                    Some(Some(_)) => (RECORD_FLAGS, "service records"),
+                    _ => (PublishFlags::empty(), "services without a TTL"),
+                };
+                parse_flags(value.into_inner())
+                    .and_then(|flags| {
+                        check_flags(flags, allowed, entries)?;
+                        Ok(flags)
+                    })
+                    .map_err(|e| report(&mut self.diagnostics, span, e))
+                    .ok()
+            }
+            None => Some(PublishFlags::empty()),
+        };
+
+        let alias = match raw.alias {
+            Some(alias) => self.alias(source, alias).map(Some),
+            None => Some(None),
+        };
+
+        if let (
+            true,
+            Some(alias),
+            Some(kind),
+            Some(protocol),
+            Some(port),
+            Some(txt),
+            Some(ttl),
+            Some(flags),
+        ) = (valid, alias, kind, protocol, port, txt, ttl, flags)
+        {
+            let service = ServiceConfig {
+                alias,
+                kind,
+                protocol,
+                port,
+                txt,
+                ttl,
+                flags,
+            };
+            self.services
+                .insert(name.clone(), source.position(name_span));
+            self.config.services.insert(name, service);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avahi_zbus::{PublishFlags, Ttl};
+
+    use super::{AliasMode, AliasTarget, Config, ConfigError};
+    use crate::name::NameEncoding;
+
+    #[test]
+    fn diagnostics() {
+        let contents = r#"aliases = ["git.local", "git.local", "nas.lan", "a..local"]
+
+[services]
+vault = { alias = "vault.local", kind = "https", protocol = "tcp", port = 443 }
+web = { alias = "git.local", kind = "Http", protocol = "sctp", port = 0 }
+"#;
+
+        let Err(ConfigError::Invalid(diagnostics)) = Config::parse("config.toml", contents) else {
+            panic!("config should be invalid");
+        };
+        let messages = diagnostics
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "config.toml:1:25: Alias git.local is already defined at config.toml:1:12",
+                "config.toml:1:38: Alias nas.lan is outside of the .local domain",
+                "config.toml:1:49: Invalid alias \"a..local\": Empty label not allowed",
+                "config.toml:5:17: Alias git.local is already defined at config.toml:1:12",
+                "config.toml:5:37: Invalid service kind \"Http\": Service type contains invalid characters",
+                "config.toml:5:56: Invalid transport protocol \"sctp\", expected tcp or udp",
+                "config.toml:5:71: Port 0 is not between 1 and 65535",
+            ]
+        );
+    }
+
+    #[test]
+    fn address_aliases() {
+        let contents = r#"aliases = ["git.local", { name = "nas.local", address = "fe80::1" }]"#;
+        let config = Config::parse("config.toml", contents).unwrap();
+
+        assert_eq!(
+            config.aliases[0].target,
+            AliasTarget::Host(AliasMode::Cname)
+        );
+        assert_eq!(config.aliases[1].name.to_string(), "nas.local");
+        assert_eq!(
+            config.aliases[1].target,
+            AliasTarget::Address("fe80::1".parse().unwrap())
+        );
+
+        let contents = r#"aliases = [{ name = "nas.local", address = "192.168.1.300" }]"#;
+        let Err(ConfigError::Invalid(diagnostics)) = Config::parse("config.toml", contents) else {
+            panic!("config should be invalid");
+        };
+
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "config.toml:1:12: Invalid address \"192.168.1.300\": invalid IP address syntax"
+        );
+    }
+
+    #[test]
+    fn alias_modes() {
+        let contents = r#"aliases = ["git.local", { name = "nas.local", mode = "cname" }]
+alias_mode = "addresses"
+
+[services]
+vault = { alias = "vault.local", kind = "https", protocol = "tcp", port = 443 }
+"#;
+        let config = Config::parse("config.toml", contents).unwrap();
+        let mut targets = config
+            .all_aliases()
+            .map(|alias| (alias.name.to_string(), alias.target))
+            .collect::<Vec<_>>();
+        targets.sort();
+
+        assert_eq!(
+            targets,
+            [
+                (
+                    "git.local".to_owned(),
+                    AliasTarget::Host(AliasMode::Addresses)
+                ),
+                ("nas.local".to_owned(), AliasTarget::Host(AliasMode::Cname)),
+                (
+                    "vault.local".to_owned(),
+                    AliasTarget::Host(AliasMode::Addresses)
+                ),
+            ]
+        );
+        assert_eq!(toml::from_str::<Config>(contents).unwrap(), config);
+        let serialized = toml::to_string(&config).unwrap();
+        assert_eq!(Config::parse("config.toml", &serialized).unwrap(), config);
+
+        let contents = r#"alias_mode = "a"
+aliases = [{ name = "nas.local", address = "fe80::1", mode = "addresses" }]"#;
+        let Err(ConfigError::Invalid(diagnostics)) = Config::parse("config.toml", contents) else {
+            panic!("config should be invalid");
+        };
+        let messages = diagnostics
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "config.toml:1:14: Invalid alias mode \"a\", expected cname or addresses",
+                "config.toml:2:12: Alias cannot have both an address and a mode",
+            ]
+        );
+    }
+
+    #[test]
+    fn name_encoding() {
+        let contents = r#"name_encoding = "idna"
+aliases = ["küche.local", 'Living\.Room.local']"#;
+        let config = Config::parse("config.toml", contents).unwrap();
+
+        assert_eq!(config.name_encoding, NameEncoding::Idna);
+        assert_eq!(config.aliases[0].name.to_string(), "küche.local");
+        assert_eq!(config.aliases[1].name.label_count(), 2);
+        assert_eq!(config.aliases[1].name.to_string(), r"Living\.Room.local");
+
+        let contents = r#"name_encoding = "idna"
+aliases = ["ü\uFFFD.local"]
+
+[[records]]
+name = "b._dns-sd._udp.local"
+type = "PTR"
+target = "ü\uFFFD.local"
+"#;
+        let Err(ConfigError::Invalid(diagnostics)) = Config::parse("config.toml", contents) else {
+            panic!("config should be invalid");
+        };
+        let messages = diagnostics
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "config.toml:2:12: ü\u{fffd}.local cannot be encoded as idna: Label \"ü\u{fffd}\" is not a valid internationalized label",
+                "config.toml:4:1: ü\u{fffd}.local cannot be encoded as idna: Label \"ü\u{fffd}\" is not a valid internationalized label",
+            ]
+        );
+
+        let contents = r#"name_encoding = "punycode""#;
+        let Err(ConfigError::Invalid(diagnostics)) = Config::parse("config.toml", contents) else {
+            panic!("config should be invalid");
+        };
+
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "config.toml:1:17: Invalid name encoding \"punycode\", expected utf8 or idna"
+        );
+    }
+
+    #[test]
+    fn ttls() {
+        let contents = r#"aliases = [{ name = "git.local", ttl = "2h" }, { name = "nas.local", address = "fe80::1", ttl = 90 }]
+alias_mode = "addresses"
+
+[services]
+vault = { alias = "vault.local", kind = "https", protocol = "tcp", port = 443, ttl = "1h30m" }
+
+[[records]]
+name = "nas.local"
+type = "HINFO"
+ttl = "1d"
+cpu = "ARM"
+os = "Linux"
+"#;
+        let config = Config::parse("config.toml", contents).unwrap();
+        let mut aliases = config
+            .all_aliases()
+            .map(|alias| alias.to_string())
+            .collect::<Vec<_>>();
+        aliases.sort();
+
+        assert_eq!(
+            aliases,
+            [
+                "git.local -> host addresses (TTL 2h)",
+                "nas.local -> fe80::1 (TTL 1m30s)",
+                "vault.local -> host addresses (TTL 1h30m)",
+            ]
+        );
+        assert_eq!(config.services["vault"].ttl, Some(Ttl::from_secs(5400)));
+        assert_eq!(config.records[0].ttl, Ttl::DAY);
+
+        let contents = r#"aliases = [{ name = "git.local", ttl = "2x" }]
+
+[services]
+vault = { kind = "https", protocol = "tcp", port = 443, ttl = "8d" }
+
+[[records]]
+name = "nas.local"
+type = "PTR"
+ttl = -1
+target = "nas.local"
+"#;
+        let Err(ConfigError::Invalid(diagnostics)) = Config::parse("config.toml", contents) else {
+            panic!("config should be invalid");
+        };
+        let messages = diagnostics
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                r#"config.toml:1:12: Invalid TTL "2x", expected seconds or a duration like 75m"#,
+                "config.toml:4:63: TTL 8d exceeds the maximum of 7d",
+                "config.toml:6:1: Invalid record nas.local: Invalid TTL -1",
+            ]
+        );
+    }
+
+    #[test]
+    fn flags() {
+        let contents = r#"aliases = [{ name = "printer.local", address = "192.168.1.20", flags = ["no_probe", "allow_multiple"] }]
+
+[services]
+vault = { kind = "https", protocol = "tcp", port = 443, ttl = "1h", flags = ["no_probe"] }
+
+[[records]]
+name = "nas.local"
+type = "TXT"
+flags = ["unique"]
+txt = { model = "ds220" }
+"#;
+        let config = Config::parse("config.toml", contents).unwrap();
+
+        assert_eq!(
+            config.aliases[0].flags,
+            PublishFlags::NO_PROBE | PublishFlags::ALLOW_MULTIPLE
+        );
+        assert_eq!(
+            config.aliases[0].to_string(),
+            "printer.local -> 192.168.1.20 (NO_PROBE | ALLOW_MULTIPLE)"
+        );
+        assert_eq!(config.services["vault"].flags, PublishFlags::NO_PROBE);
+        assert_eq!(config.records[0].flags, PublishFlags::UNIQUE);
+        assert_eq!(toml::from_str::<Config>(contents).unwrap(), config);
+        let serialized = toml::to_string(&config).unwrap();
+        assert!(serialized.contains(r#"flags = ["no_probe", "allow_multiple"]"#));
+        assert_eq!(Config::parse("config.toml", &serialized).unwrap(), config);
+
+        let contents = r#"alias_mode = "addresses"
+aliases = [
+    { name = "git.local", flags = ["update"] },
+    { name = "nas.local", flags = ["unique", "no_probe", "allow_multiple"] },
+    { name = "cloud.local", mode = "cname", flags = ["no_reverse"] },
+    { name = "tv.local", ttl = 60, flags = ["unique"] },
+]
+
+[services]
+vault = { kind = "https", protocol = "tcp", port = 443, flags = "unique" }
+web = { kind = "http", protocol = "tcp", port = 80, flags = ["no_probe"] }
+ssh = { kind = "ssh", protocol = "tcp", port = 22, ttl = 60, flags = ["no_reverse"] }
+
+[[records]]
+name = "nas.local"
+type = "TXT"
+flags = ["no_reverse"]
+"#;
+        let Err(ConfigError::Invalid(diagnostics)) = Config::parse("config.toml", contents) else {
+            panic!("config should be invalid");
+        };
+        let messages = diagnostics
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                r#"config.toml:3:5: Unknown flag "update", expected one of unique, no_probe, no_announce, allow_multiple, no_reverse"#,
+                "config.toml:4:5: Invalid alias nas.local: Flags unique, allow_multiple cannot be set on host addresses",
+                "config.toml:5:5: Invalid alias cloud.local: Flag no_reverse cannot be set on records",
+                r#"config.toml:10:65: Flags must be a list like ["no_probe"]"#,
+                "config.toml:11:61: Flag no_probe cannot be set on services without a TTL",
+                "config.toml:12:70: Flag no_reverse cannot be set on service records",
+                "config.toml:14:1: Invalid record nas.local: Flag no_reverse cannot be set on records",
+            ]
+        );
+    }
+
+    #[test]
+    fn records() {
+        let contents = r#"[[records]]
+name = "_minecraft._tcp.local"
+type = "SRV"
+ttl = 120
+weight = 5
+port = 25565
+target = "mc.local"
+
+[[records]]
+name = "b._dns-sd._udp.local"
+type = "PTR"
+target = "lan.local"
+
+[[records]]
+name = "nas.local"
+type = "HINFO"
+cpu = "ARM"
+os = "Linux"
+"#;
+        let config = Config::parse("config.toml", contents).unwrap();
+        let records = config
+            .records
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            records,
+            [
+                "_minecraft._tcp.local 120 SRV 0 5 25565 mc.local",
+                "b._dns-sd._udp.local 4500 PTR lan.local",
+                r#"nas.local 4500 HINFO "ARM" "Linux""#,
+            ]
+        );
+
+        let contents = r#"[[records]]
+name = "a.local"
+type = "MX"
+
+[[records]]
+name = "b.local"
+type = "SRV"
+port = 80
+target = "b.local"
+priority = -1
+
+[[records]]
+name = "c.local"
+type = "PTR"
+target = "c.local"
+weight = 1
+"#;
+        let Err(ConfigError::Invalid(diagnostics)) = Config::parse("config.toml", contents) else {
+            panic!("config should be invalid");
+        };
+        let messages = diagnostics
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "config.toml:1:1: Invalid record a.local: unknown variant `MX`, expected one of `TXT`, `SRV`, `PTR`, `HINFO`",
+                "config.toml:5:1: Invalid record b.local: invalid value: integer `-1`, expected u16",
+                "config.toml:12:1: Invalid record c.local: unknown field `weight`, expected `target`",
+            ]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::{future::Future, ops::Deref};
+
+use avahi_zbus::{
+    AddressResolverProxy, DomainBrowserProxy, EntryGroupProxy, HostNameResolverProxy,
+    RecordBrowserProxy, ServiceBrowserProxy, ServiceResolverProxy, ServiceTypeBrowserProxy,
+};
+use tokio::runtime;
+use tracing::debug;
+use zbus::Proxy;
+
+/// An object which lives in avahi-daemon until it is freed.
+pub trait Free: Send + Sync {
+    fn free(&self) -> impl Future<Output = Result<(), zbus::Error>> + Send;
+}
+
+macro_rules! impl_free {
+    ($($proxy:ident),*) => {
+        $(
+            impl Free for $proxy<'_> {
+                async fn free(&self) -> Result<(), zbus::Error> {
+                    $proxy::free(self).await
+                }
+            }
+        )*
+    };
+}
+
+impl_free!(
+    EntryGroupProxy,
+    ServiceBrowserProxy,
+    ServiceTypeBrowserProxy,
+    DomainBrowserProxy,
+    RecordBrowserProxy,
+    HostNameResolverProxy,
+    AddressResolverProxy,
+    ServiceResolverProxy
+);
+
+/// Owns an avahi object and frees it once dropped, even if the owning task was cancelled.
+///
+/// Dropping spawns the call on the current tokio runtime,
+/// without a runtime the object lives until avahi notices the connection is gone.
+#[derive(Debug)]
+pub struct Handle<P: Free + 'static> {
+    proxy: Option<P>,
+}
+
+impl<P: Free + 'static> Handle<P> {
+    pub fn new(proxy: P) -> Self {
+        Self { proxy: Some(proxy) }
+    }
+
+    /// Frees the object right away, reporting errors instead of just logging them.
+    pub async fn free(mut self) -> Result<(), zbus::Error> {
+        match self.proxy.take() {
+            Some(proxy) => proxy.free().await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl<P: Free + 'static> Deref for Handle<P> {
+    type Target = P;
+
+    fn deref(&self) -> &Self::Target {
+        self.proxy.as_ref().expect("only taken when consumed")
+    }
+}
+
+impl<'p, P> AsRef<Proxy<'p>> for Handle<P>
+where
+    P: Free + 'static + AsRef<Proxy<'p>>,
+{
+    fn as_ref(&self) -> &Proxy<'p> {
+        (**self).as_ref()
+    }
+}
+
+impl<P: Free + 'static> Drop for Handle<P> {
+    fn drop(&mut self) {
+        let Some(proxy) = self.proxy.take() else {
+            return;
+        };
+        let Ok(runtime) = runtime::Handle::try_current() else {
+            return;
+        };
+
+        runtime.spawn(async move {
+            if let Err(e) = proxy.free().await {
+                debug!("Failed to free dropped avahi object: {e}");
+            }
+        });
+    }
+}
@@ -0,0 +1,47 @@
+use std::{fmt, net::IpAddr};
+
+use avahi_zbus::{DnsType, InterfaceIndex, Protocol};
+use zbus::zvariant::Optional;
+
+use crate::rdata::{Aaaa, A};
+
+/// An address of a host as published on one interface and protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HostAddress {
+    pub interface: Optional<InterfaceIndex>,
+    pub protocol: Protocol,
+    pub address: IpAddr,
+}
+
+impl HostAddress {
+    /// Decodes the rdata of an A or AAAA record, returns `None` for any other or malformed record.
+    pub fn from_rdata(
+        interface: Optional<InterfaceIndex>,
+        protocol: Protocol,
+        kind: DnsType,
+        rdata: &[u8],
+    ) -> Option<Self> {
+        let address = match kind {
+            DnsType::A => IpAddr::from(A::decode(rdata).ok()?.as_addr()),
+            DnsType::AAAA => IpAddr::from(Aaaa::decode(rdata).ok()?.as_addr()),
+            _ => return None,
+        };
+
+        Some(Self {
+            interface,
+            protocol,
+            address,
+        })
+    }
+}
+
+impl fmt::Display for HostAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.address)?;
+
+        match *self.interface {
+            Some(InterfaceIndex(index)) => write!(f, " on {index}/{:?}", self.protocol),
+            None => write!(f, " on */{:?}", self.protocol),
+        }
+    }
+}
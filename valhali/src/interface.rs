@@ -0,0 +1,282 @@
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use avahi_zbus::{AvahiError, InterfaceIndex, ServerProxy};
+use tokio::fs;
+use zbus::{zvariant::Optional, Connection};
+
+/// Where interface names are listed, avahi itself cannot enumerate them.
+const SYSFS_NET: &str = "/sys/class/net";
+
+/// Selects the network interface to publish on, browse or resolve.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Interface {
+    /// Every interface avahi uses.
+    #[default]
+    Any,
+    Index(InterfaceIndex),
+    /// The name of an interface like `eth0`, resolved to its index through avahi.
+    Name(String),
+}
+
+impl Interface {
+    /// The index as passed to avahi, `-1` standing for any interface.
+    ///
+    /// Names are looked up on every call,
+    /// the helpers of this crate take [`Interfaces`] to keep them.
+    pub async fn resolve(
+        &self,
+        connection: &Connection,
+    ) -> Result<Optional<InterfaceIndex>, zbus::Error> {
+        let index = match self {
+            Self::Any => return Ok(Optional::default()),
+            Self::Index(index) => *index,
+            Self::Name(name) => {
+                let server = ServerProxy::new(connection).await?;
+                server.get_network_interface_index_by_name(name).await?
+            }
+        };
+
+        Ok(Some(index).into())
+    }
+
+    /// The name of the interface, `None` for any interface.
+    pub async fn name(&self, connection: &Connection) -> Result<Option<String>, zbus::Error> {
+        match self {
+            Self::Any => Ok(None),
+            Self::Name(name) => Ok(Some(name.clone())),
+            Self::Index(index) => {
+                let server = ServerProxy::new(connection).await?;
+                let name = server.get_network_interface_name_by_index(*index).await?;
+                Ok(Some(name))
+            }
+        }
+    }
+
+    /// Lists the network interfaces of this host known to avahi, ordered by their index.
+    ///
+    /// Names are read from `/sys/class/net`, so this only works on Linux.
+    pub async fn list(
+        connection: &Connection,
+    ) -> Result<Vec<(InterfaceIndex, String)>, zbus::Error> {
+        let mut entries = fs::read_dir(SYSFS_NET).await?;
+        let mut interfaces = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await? {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+
+            // interfaces may disappear in the meantime or be ignored by avahi
+            let index = match Self::Name(name.clone()).resolve(connection).await {
+                Ok(index) => index,
+                Err(e) => match AvahiError::from_zbus(&e) {
+                    Some(AvahiError::InvalidInterface | AvahiError::NotFound) => continue,
+                    _ => return Err(e),
+                },
+            };
+            if let Some(index) = *index {
+                interfaces.push((index, name));
+            }
+        }
+
+        interfaces.sort();
+        Ok(interfaces)
+    }
+}
+
+/// Interface indices and names resolved through avahi over one connection.
+///
+/// Interfaces may be recreated with another index, e.g. for a VPN or USB device,
+/// so entries are dropped as soon as avahi reports them as unknown.
+#[derive(Debug, Clone)]
+pub struct Interfaces {
+    connection: Connection,
+    cache: Arc<Mutex<Cache>>,
+}
+
+#[derive(Debug, Default)]
+struct Cache {
+    indices: BTreeMap<String, InterfaceIndex>,
+    names: BTreeMap<InterfaceIndex, String>,
+}
+
+impl Cache {
+    fn insert(&mut self, index: InterfaceIndex, name: &str) {
+        self.indices.insert(name.to_owned(), index);
+        self.names.insert(index, name.to_owned());
+    }
+
+    fn remove(&mut self, interface: &Interface) {
+        let (index, name) = match interface {
+            Interface::Any => return,
+            Interface::Index(index) => (Some(*index), self.names.get(index).cloned()),
+            Interface::Name(name) => (self.indices.get(name).copied(), Some(name.clone())),
+        };
+
+        if let Some(index) = index {
+            self.names.remove(&index);
+        }
+        if let Some(name) = name {
+            self.indices.remove(&name);
+        }
+    }
+}
+
+impl Interfaces {
+    pub fn new(connection: &Connection) -> Self {
+        Self {
+            connection: connection.clone(),
+            cache: Arc::default(),
+        }
+    }
+
+    /// Like [`Interface::resolve`], looking names up once.
+    pub async fn resolve(
+        &self,
+        interface: &Interface,
+    ) -> Result<Optional<InterfaceIndex>, zbus::Error> {
+        if let Interface::Name(name) = interface {
+            if let Some(index) = self.cache().indices.get(name) {
+                return Ok(Some(*index).into());
+            }
+        }
+
+        let index = self.check(interface, interface.resolve(&self.connection).await)?;
+        if let (Interface::Name(name), Some(index)) = (interface, *index) {
+            self.cache().insert(index, name);
+        }
+        Ok(index)
+    }
+
+    /// Like [`Interface::name`], looking indices up once.
+    pub async fn name(&self, interface: &Interface) -> Result<Option<String>, zbus::Error> {
+        if let Interface::Index(index) = interface {
+            if let Some(name) = self.cache().names.get(index) {
+                return Ok(Some(name.clone()));
+            }
+        }
+
+        let name = self.check(interface, interface.name(&self.connection).await)?;
+        if let (Interface::Index(index), Some(name)) = (interface, &name) {
+            self.cache().insert(*index, name);
+        }
+        Ok(name)
+    }
+
+    /// Drops the cached index and name of an interface
+    /// if avahi reported it as unknown, e.g. in the result of a call using its index.
+    pub fn check<T>(
+        &self,
+        interface: &Interface,
+        result: Result<T, zbus::Error>,
+    ) -> Result<T, zbus::Error> {
+        if let Err(e) = &result {
+            if let Some(AvahiError::InvalidInterface | AvahiError::NotFound) =
+                AvahiError::from_zbus(e)
+            {
+                self.cache().remove(interface);
+            }
+        }
+
+        result
+    }
+
+    fn cache(&self) -> MutexGuard<'_, Cache> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl From<InterfaceIndex> for Interface {
+    fn from(value: InterfaceIndex) -> Self {
+        Self::Index(value)
+    }
+}
+
+impl From<Optional<InterfaceIndex>> for Interface {
+    fn from(value: Optional<InterfaceIndex>) -> Self {
+        match *value {
+            Some(index) => Self::Index(index),
+            None => Self::Any,
+        }
+    }
+}
+
+/// `*` or nothing selects any interface, numbers an index and anything else a name.
+impl FromStr for Interface {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "" | "*" => Self::Any,
+            s => match s.parse() {
+                Ok(index) => Self::Index(InterfaceIndex(index)),
+                Err(_) => Self::Name(s.to_owned()),
+            },
+        })
+    }
+}
+
+impl fmt::Display for Interface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => f.write_str("*"),
+            Self::Index(InterfaceIndex(index)) => write!(f, "{index}"),
+            Self::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avahi_zbus::InterfaceIndex;
+    use zbus::zvariant::Optional;
+
+    use super::{Cache, Interface};
+
+    #[test]
+    fn parse() {
+        let cases = [
+            ("", Interface::Any),
+            ("*", Interface::Any),
+            ("2", Interface::Index(InterfaceIndex(2))),
+            ("eth0", Interface::Name("eth0".to_owned())),
+            ("70000", Interface::Name("70000".to_owned())),
+        ];
+
+        for (s, interface) in cases {
+            assert_eq!(s.parse::<Interface>(), Ok(interface));
+        }
+
+        assert_eq!(Interface::Index(InterfaceIndex(3)).to_string(), "3");
+        assert_eq!(Interface::Any.to_string(), "*");
+        assert_eq!(
+            Interface::from(Optional::<InterfaceIndex>::default()),
+            Interface::Any
+        );
+        assert_eq!(InterfaceIndex::try_from(-1), Err(-1));
+        assert_eq!(InterfaceIndex::try_from(4), Ok(InterfaceIndex(4)));
+    }
+
+    #[test]
+    fn cache() {
+        let mut cache = Cache::default();
+        cache.insert(InterfaceIndex(2), "eth0");
+        cache.insert(InterfaceIndex(3), "wg0");
+
+        cache.remove(&Interface::Name("wg0".to_owned()));
+        assert_eq!(cache.indices.get("wg0"), None);
+        assert_eq!(cache.names.get(&InterfaceIndex(3)), None);
+
+        cache.remove(&Interface::Index(InterfaceIndex(2)));
+        assert!(cache.indices.is_empty());
+        assert!(cache.names.is_empty());
+
+        cache.remove(&Interface::Any);
+    }
+}
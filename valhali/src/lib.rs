@@ -1,25 +1,35 @@
-use std::time::Duration;
+use std::{collections::HashSet, future::Future, net::IpAddr, str::FromStr, time::Duration};
 
 use avahi_zbus::{
-    DnsClass, EntryGroupProxy, EntryGroupState, Protocol, ResolveHostNameResponse, ServerProxy,
-    ServerState, Ttl,
+    AvahiError, BrowseEvent, DnsClass, DnsType, EntryGroupProxy, EntryGroupState, InterfaceIndex,
+    LookupFlags, Protocol, PublishFlags, ResolveHostNameResponse, ResolveServiceResponse, Server,
+    ServerProxy, ServerState, Ttl,
 };
-use name::Name;
-use rdata::RecordData;
+use handle::Handle;
+use host::HostAddress;
+use interface::{Interface, Interfaces};
+use name::{Name, NameBuf};
+use rdata::{Aaaa, Ptr, RecordData, Srv, A};
 use record::Record;
-use service::Service;
-use tokio::{task::JoinHandle, time};
+use service::{Service, ServiceInstance};
+use tokio::{sync::watch, task::JoinHandle, time};
+use tracing::warn;
 use zbus::{export::futures_util::StreamExt, zvariant::Optional};
 
+pub mod config;
+pub mod handle;
+pub mod host;
+pub mod interface;
 pub mod name;
 pub mod rdata;
 pub mod record;
 pub mod service;
 pub mod status;
+pub mod txt;
 
 pub async fn entry_group_event_handler(
     group: &EntryGroupProxy<'_>,
-    f: impl Fn(&EntryGroupState, &str) + Send + Sync + 'static,
+    f: impl Fn(&EntryGroupState, Option<AvahiError>) + Send + Sync + 'static,
 ) -> JoinHandle<Result<(), zbus::Error>> {
     let rx = group.receive_state_changed().await;
 
@@ -29,9 +39,8 @@ pub async fn entry_group_event_handler(
         while let Some(signal) = rx.next().await {
             let args = signal.args()?;
 
-            let state = args.state();
-            let error = args.error();
-            f(state, error);
+            let error = AvahiError::from_state_error(args.error());
+            f(args.state(), error);
         }
 
         Ok(())
@@ -40,6 +49,37 @@ pub async fn entry_group_event_handler(
 
 pub async fn entry_group_add_record<D>(
     group: &EntryGroupProxy<'_>,
+    interfaces: &Interfaces,
+    interface: &Interface,
+    record: &Record<D>,
+) -> Result<(), zbus::Error>
+where
+    D: RecordData,
+{
+    on_interface(interfaces, interface, |interface| {
+        add_record(group, interface, Protocol::Unspec, record)
+    })
+    .await
+}
+
+/// Calls avahi with the index of an interface,
+/// dropping it from `interfaces` if avahi reports the interface as unknown.
+async fn on_interface<T, F>(
+    interfaces: &Interfaces,
+    interface: &Interface,
+    f: impl FnOnce(Optional<InterfaceIndex>) -> F,
+) -> Result<T, zbus::Error>
+where
+    F: Future<Output = Result<T, zbus::Error>>,
+{
+    let index = interfaces.resolve(interface).await?;
+    interfaces.check(interface, f(index).await)
+}
+
+async fn add_record<D>(
+    group: &EntryGroupProxy<'_>,
+    interface: Optional<InterfaceIndex>,
+    protocol: Protocol,
     record: &Record<D>,
 ) -> Result<(), zbus::Error>
 where
@@ -47,35 +87,96 @@ where
 {
     group
         .add_record(
-            Optional::default(),
-            Protocol::Unspec,
-            0,
+            interface,
+            protocol,
+            record.flags,
             &record.name.to_string(),
             DnsClass::IN,
             D::KIND,
             record.ttl,
-            record.data.as_rdata(),
+            &record.data.to_rdata(),
         )
         .await
 }
 
+/// Publishes one address of the host under another name on the same interface and protocol,
+/// without the reverse PTR record which is already owned by the host itself.
+///
+/// Avahi publishes addresses with the TTL of host names,
+/// a different `ttl` publishes a plain A or AAAA record instead, probed as unique all the same.
+pub async fn entry_group_add_address(
+    group: &EntryGroupProxy<'_>,
+    name: &Name,
+    address: &HostAddress,
+    ttl: Option<Ttl>,
+    flags: PublishFlags,
+) -> Result<(), zbus::Error> {
+    let interface = address.interface.clone();
+
+    match (ttl, address.address) {
+        (None, _) => {
+            group
+                .add_address(
+                    interface,
+                    address.protocol,
+                    flags | PublishFlags::NO_REVERSE,
+                    &name.to_string(),
+                    &address.address.to_string(),
+                )
+                .await
+        }
+        (Some(ttl), IpAddr::V4(ip)) => {
+            let flags = address_record_flags(flags);
+            let record = Record::new(name.to_owned(), ttl, A::from(ip)).with_flags(flags);
+            add_record(group, interface, address.protocol, &record).await
+        }
+        (Some(ttl), IpAddr::V6(ip)) => {
+            let flags = address_record_flags(flags);
+            let record = Record::new(name.to_owned(), ttl, Aaaa::from(ip)).with_flags(flags);
+            add_record(group, interface, address.protocol, &record).await
+        }
+    }
+}
+
+/// The flags of an address published as a plain record,
+/// unique like the addresses avahi publishes itself.
+/// Records never have a reverse PTR record, avahi rejects the flag for them.
+fn address_record_flags(flags: PublishFlags) -> PublishFlags {
+    (flags - PublishFlags::NO_REVERSE) | PublishFlags::UNIQUE
+}
+
 pub async fn entry_group_add_service(
     group: &EntryGroupProxy<'_>,
+    interfaces: &Interfaces,
+    interface: &Interface,
     service: &Service,
 ) -> Result<(), zbus::Error> {
-    let ty = format!("_{}._{}", service.kinds[0].as_str(), service.protocol);
+    on_interface(interfaces, interface, |interface| {
+        add_service(group, interface, service)
+    })
+    .await
+}
+
+async fn add_service(
+    group: &EntryGroupProxy<'_>,
+    interface: Optional<InterfaceIndex>,
+    service: &Service,
+) -> Result<(), zbus::Error> {
+    let ty = service.service_type();
+    let txt = service.txt.encode();
+    let txt = txt.iter().map(Vec::as_slice).collect::<Vec<_>>();
 
     group
         .add_service(
-            Optional::default(),
+            interface.clone(),
             Protocol::Unspec,
-            0,
+            service.flags,
             &service.name,
             &ty,
             "",
             "",
             service.port,
-            &[],
+            &txt,
         )
         .await?;
 
@@ -84,9 +185,9 @@ pub async fn entry_group_add_service(
 
         group
             .add_service_subtype(
-                Optional::default(),
+                interface.clone(),
                 Protocol::Unspec,
-                0,
+                service.flags,
                 &service.name,
                 &ty,
                 "",
@@ -98,9 +199,87 @@ pub async fn entry_group_add_service(
     Ok(())
 }
 
+/// Publishes a service as its individual PTR, SRV and TXT records pointing at `host`,
+/// as avahi publishes whole services with fixed TTLs.
+pub async fn entry_group_add_service_records(
+    group: &EntryGroupProxy<'_>,
+    interfaces: &Interfaces,
+    interface: &Interface,
+    service: &Service,
+    host: &Name,
+    ttl: Ttl,
+) -> Result<(), zbus::Error> {
+    on_interface(interfaces, interface, |interface| {
+        add_service_records(group, interface, service, host, ttl)
+    })
+    .await
+}
+
+async fn add_service_records(
+    group: &EntryGroupProxy<'_>,
+    interface: Optional<InterfaceIndex>,
+    service: &Service,
+    host: &Name,
+    ttl: Ttl,
+) -> Result<(), zbus::Error> {
+    let invalid = |e: name::NameError| zbus::Error::Failure(format!("Service {service}: {e}"));
+    let type_name = service.type_name().map_err(invalid)?;
+    let instance = service.instance_name().map_err(invalid)?;
+    let services = NameBuf::from_str("_services._dns-sd._udp.local").map_err(invalid)?;
+
+    // the pointers are shared with other hosts, the SRV and TXT records belong to this instance
+    let shared = service.flags - (PublishFlags::UNIQUE | PublishFlags::ALLOW_MULTIPLE);
+    let enumeration = Record::new(services, ttl, Ptr(type_name.clone())).with_flags(shared);
+    add_record(group, interface.clone(), Protocol::Unspec, &enumeration).await?;
+    let ptr = Record::new(type_name, ttl, Ptr(instance.clone())).with_flags(shared);
+    add_record(group, interface.clone(), Protocol::Unspec, &ptr).await?;
+    for sub_type in service.sub_type_names().map_err(invalid)? {
+        let ptr = Record::new(sub_type, ttl, Ptr(instance.clone())).with_flags(shared);
+        add_record(group, interface.clone(), Protocol::Unspec, &ptr).await?;
+    }
+
+    let unique = service.flags | PublishFlags::UNIQUE;
+    let srv = Srv {
+        priority: 0,
+        weight: 0,
+        port: service.port,
+        target: host.to_owned(),
+    };
+    let srv = Record::new(instance.clone(), ttl, srv).with_flags(unique);
+    add_record(group, interface.clone(), Protocol::Unspec, &srv).await?;
+    let txt = Record::new(instance, ttl, service.txt.clone()).with_flags(unique);
+    add_record(group, interface, Protocol::Unspec, &txt).await
+}
+
+/// Replaces the TXT record of an already published service,
+/// without withdrawing and probing the service again.
+pub async fn entry_group_update_service_txt(
+    group: &EntryGroupProxy<'_>,
+    interfaces: &Interfaces,
+    interface: &Interface,
+    service: &Service,
+) -> Result<(), zbus::Error> {
+    let ty = service.service_type();
+    let txt = service.txt.encode();
+    let txt = txt.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+    on_interface(interfaces, interface, |interface| {
+        group.update_service_txt(
+            interface,
+            Protocol::Unspec,
+            PublishFlags::empty(),
+            &service.name,
+            &ty,
+            "",
+            &txt,
+        )
+    })
+    .await
+}
+
 pub async fn server_event_handler(
     server: &ServerProxy<'_>,
-    f: impl Fn(&ServerState, &str) + Send + Sync + 'static,
+    f: impl Fn(&ServerState, Option<AvahiError>) + Send + Sync + 'static,
 ) -> JoinHandle<Result<(), zbus::Error>> {
     let rx = server.receive_state_changed().await;
 
@@ -110,34 +289,217 @@ pub async fn server_event_handler(
         while let Some(signal) = rx.next().await {
             let args = signal.args()?;
 
-            let state = args.state();
-            let error = args.error();
-            f(state, error);
+            let error = AvahiError::from_state_error(args.error());
+            f(args.state(), error);
         }
 
         Ok(())
     })
 }
 
+/// Keeps `tx` up to date with the addresses published for the host name
+/// by browsing its A and AAAA records.
+/// Receivers are notified once the cached addresses are known and on every change afterwards.
+pub async fn server_browse_host_addresses(
+    server: &Server,
+    interfaces: &Interfaces,
+    interface: &Interface,
+    name: &Name,
+    tx: watch::Sender<HashSet<HostAddress>>,
+) -> Result<JoinHandle<Result<(), zbus::Error>>, zbus::Error> {
+    let (inet, inet6) = on_interface(interfaces, interface, |interface| async move {
+        let inet = browse_host_addresses(server, &interface, name, DnsType::A, tx.clone()).await?;
+        let inet6 = browse_host_addresses(server, &interface, name, DnsType::AAAA, tx).await?;
+        Ok((inet, inet6))
+    })
+    .await?;
+
+    Ok(tokio::spawn(async move {
+        tokio::try_join!(inet, inet6)?;
+        Ok(())
+    }))
+}
+
+async fn browse_host_addresses(
+    server: &Server,
+    interface: &Optional<InterfaceIndex>,
+    name: &Name,
+    kind: DnsType,
+    tx: watch::Sender<HashSet<HostAddress>>,
+) -> Result<impl Future<Output = Result<(), zbus::Error>>, zbus::Error> {
+    let mut browser = server
+        .record_browser(
+            interface.clone(),
+            Protocol::Unspec,
+            &name.to_string(),
+            DnsClass::IN,
+            kind,
+            LookupFlags::empty(),
+        )
+        .await?
+        .map_proxy(Handle::new);
+
+    Ok(async move {
+        // collect the cached addresses first instead of notifying for each of them
+        let mut ready = false;
+
+        while let Some(event) = browser.next().await {
+            match event {
+                BrowseEvent::New(record) => {
+                    if let Some(address) = HostAddress::from_rdata(
+                        record.interface,
+                        record.protocol,
+                        record.type_,
+                        &record.rdata,
+                    ) {
+                        tx.send_if_modified(|addresses| addresses.insert(address) && ready);
+                    }
+                }
+                BrowseEvent::Remove(record) => {
+                    if let Some(address) = HostAddress::from_rdata(
+                        record.interface,
+                        record.protocol,
+                        record.type_,
+                        &record.rdata,
+                    ) {
+                        tx.send_if_modified(|addresses| addresses.remove(&address) && ready);
+                    }
+                }
+                BrowseEvent::AllForNow => {
+                    if !ready {
+                        ready = true;
+                        tx.send_modify(|_| ());
+                    }
+                }
+                BrowseEvent::CacheExhausted => (),
+                BrowseEvent::Failure(error) => return Err(zbus::Error::Failure(error)),
+                BrowseEvent::Error(error) => {
+                    warn!("Dropped a signal of the {kind} browser: {error}")
+                }
+            }
+        }
+
+        browser.into_proxy().free().await
+    })
+}
+
+// https://github.com/avahi/avahi/blob/master/avahi-core/resolve-service.c#L36
+// #define TIMEOUT_MSEC 5000
+pub const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub async fn server_resolve_name(
     server: &ServerProxy<'_>,
     name: &Name,
-) -> Option<ResolveHostNameResponse> {
+    timeout: Duration,
+) -> Result<Option<ResolveHostNameResponse>, zbus::Error> {
+    resolve_host_name(server, Optional::default(), name, Protocol::Unspec, timeout).await
+}
+
+/// Resolves a host name, `None` if it was not found within the timeout.
+/// Other errors, like an unknown interface, are returned.
+pub async fn server_resolve_host_name(
+    server: &ServerProxy<'_>,
+    interfaces: &Interfaces,
+    interface: &Interface,
+    name: &Name,
+    aprotocol: Protocol,
+    timeout: Duration,
+) -> Result<Option<ResolveHostNameResponse>, zbus::Error> {
+    on_interface(interfaces, interface, |interface| {
+        resolve_host_name(server, interface, name, aprotocol, timeout)
+    })
+    .await
+}
+
+async fn resolve_host_name(
+    server: &ServerProxy<'_>,
+    interface: Optional<InterfaceIndex>,
+    name: &Name,
+    aprotocol: Protocol,
+    timeout: Duration,
+) -> Result<Option<ResolveHostNameResponse>, zbus::Error> {
     match time::timeout(
-        // https://github.com/avahi/avahi/blob/master/avahi-core/resolve-service.c#L36
-        // #define TIMEOUT_MSEC 5000
-        Duration::from_secs(5),
+        timeout,
         server.resolve_host_name(
-            Optional::default(),
+            interface,
             Protocol::Unspec,
             &name.to_string(),
+            aprotocol,
+            LookupFlags::empty(),
+        ),
+    )
+    .await
+    {
+        Ok(response) => unresolved(response),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Resolves a service instance, `None` if it was not found within the timeout.
+pub async fn server_resolve_service(
+    server: &ServerProxy<'_>,
+    interfaces: &Interfaces,
+    interface: &Interface,
+    instance: &ServiceInstance,
+    timeout: Duration,
+) -> Result<Option<ResolveServiceResponse>, zbus::Error> {
+    on_interface(interfaces, interface, |interface| {
+        resolve_service(server, interface, instance, timeout)
+    })
+    .await
+}
+
+async fn resolve_service(
+    server: &ServerProxy<'_>,
+    interface: Optional<InterfaceIndex>,
+    instance: &ServiceInstance,
+    timeout: Duration,
+) -> Result<Option<ResolveServiceResponse>, zbus::Error> {
+    match time::timeout(
+        timeout,
+        server.resolve_service(
+            interface,
+            Protocol::Unspec,
+            &instance.name,
+            &instance.service_type(),
+            instance.domain.as_deref().unwrap_or_default(),
             Protocol::Unspec,
-            0,
+            LookupFlags::empty(),
         ),
     )
     .await
     {
-        Ok(Ok(response)) => Some(response),
-        _ => None,
+        Ok(response) => unresolved(response),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Reports names and services which avahi did not find as `None`.
+fn unresolved<T>(response: Result<T, zbus::Error>) -> Result<Option<T>, zbus::Error> {
+    match response {
+        Ok(response) => Ok(Some(response)),
+        Err(e) => match AvahiError::from_zbus(&e) {
+            Some(AvahiError::Timeout | AvahiError::NotFound) => Ok(None),
+            _ => Err(e),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avahi_zbus::PublishFlags;
+
+    use super::address_record_flags;
+
+    #[test]
+    fn address_flags() {
+        assert_eq!(
+            address_record_flags(PublishFlags::NO_REVERSE),
+            PublishFlags::UNIQUE
+        );
+        assert_eq!(
+            address_record_flags(PublishFlags::NO_PROBE | PublishFlags::NO_ANNOUNCE),
+            PublishFlags::UNIQUE | PublishFlags::NO_PROBE | PublishFlags::NO_ANNOUNCE
+        );
     }
 }
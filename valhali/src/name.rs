@@ -1,45 +1,222 @@
+use idna::uts46::{AsciiDenyList, DnsLength, Hyphens, Uts46};
+use serde::{Deserialize, Serialize};
 use serde_with::{DeserializeFromStr, SerializeDisplay};
 use std::{
-    fmt,
+    borrow::{Borrow, Cow},
+    cmp::Ordering,
+    fmt::{self, Write},
+    hash::{Hash, Hasher},
+    mem,
     ops::Deref,
-    str::{from_utf8_unchecked, FromStr},
+    str::{self, FromStr},
 };
 use thiserror::Error;
 
+/// Longest name in wire format including all length prefixes and the terminating root label.
+const MAX_NAME_LEN: usize = 255;
+
+const MAX_LABEL_LEN: usize = 63;
+
+/// The end of a relative name, the root label followed by a byte marking it relative.
+const RELATIVE_END: [u8; 2] = [0, 0];
+
+/// Prefix of labels holding punycode, the ascii compatible encoding of IDNA.
+const ACE_PREFIX: &str = "xn--";
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Error)]
 pub enum NameError {
+    #[error("Empty label not allowed")]
     EmptyLabel,
+    #[error("Name has more than 253 characters")]
     LongName,
+    #[error("Label has more than 63 characters")]
     LongLabel,
+    #[error("Name ends unexpectedly")]
+    Truncated,
+    #[error("Compressed names are not supported")]
+    Compressed,
+    #[error("Label {0:?} is not valid utf-8")]
+    InvalidUtf8(Vec<u8>),
+    #[error("Invalid escape sequence in {0:?}")]
+    Escape(String),
+    #[error("Label {0:?} contains an unescaped dot")]
+    Dot(String),
+    #[error("Label {0:?} is not a valid internationalized label")]
+    Idna(String),
 }
 
-impl fmt::Display for NameError {
+/// How labels with characters outside of ascii are published.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NameEncoding {
+    /// Plain utf-8 labels, native to multicast DNS as described in
+    /// [RFC 6762 section 16](https://datatracker.ietf.org/doc/html/rfc6762#section-16).
+    #[default]
+    Utf8,
+    /// Punycode `xn--` labels as used by IDNA in unicast DNS,
+    /// for resolvers that only understand ascii names.
+    Idna,
+}
+
+/// The name as in the config, `utf8` or `idna`.
+impl fmt::Display for NameEncoding {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{self:?}")
+        match self {
+            Self::Utf8 => f.write_str("utf8"),
+            Self::Idna => f.write_str("idna"),
+        }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Label([u8]);
+impl FromStr for NameEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utf8" => Ok(Self::Utf8),
+            "idna" => Ok(Self::Idna),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+/// A single label of a [`Name`], always valid utf-8 of 1 to 63 bytes.
+/// Labels may contain any character including dots, which are escaped when displayed.
+///
+/// Labels are compared ignoring ascii case, as required by
+/// [RFC 4343](https://datatracker.ietf.org/doc/html/rfc4343).
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct Label(str);
 
 impl Label {
+    fn new(label: &str) -> &Self {
+        // SAFETY: `Label` is a `repr(transparent)` wrapper of `str`
+        unsafe { &*(label as *const str as *const Label) }
+    }
+
+    /// Splits the first label off a name in wire format.
+    ///
+    /// Only called on the validated contents of a [`Name`],
+    /// so the length prefix is always in bounds and the label is utf-8.
     fn split_from(slice: &[u8]) -> (&Self, &[u8]) {
-        let len = slice[0];
-        let (left, right) = slice[1..].split_at(len as usize);
+        let len = usize::from(slice[0]);
+        let (left, right) = slice[1..].split_at(len);
+        let label = str::from_utf8(left).expect("labels of a name are valid utf-8");
 
-        let label = unsafe { &*(left as *const [u8] as *const Label) };
+        (Self::new(label), right)
+    }
+
+    fn validate(label: &str) -> Result<(), NameError> {
+        if label.is_empty() {
+            return Err(NameError::EmptyLabel);
+        }
+        if label.len() > MAX_LABEL_LEN {
+            return Err(NameError::LongLabel);
+        }
 
-        (label, right)
+        Ok(())
     }
 
+    /// The label without any escaping.
     pub fn as_str(&self) -> &str {
-        unsafe { from_utf8_unchecked(&self.0) }
+        &self.0
+    }
+
+    /// The IDNA form of a label with any non-ascii character,
+    /// mapped and normalized as of [UTS #46](https://www.unicode.org/reports/tr46/)
+    /// and encoded as `xn--` followed by its punycode.
+    /// Ascii labels are kept as they are, including their case.
+    ///
+    /// Fails for labels with disallowed characters,
+    /// or with characters mapped to a dot like the ideographic full stop.
+    pub fn to_ascii(&self) -> Result<Cow<'_, str>, NameError> {
+        if self.0.is_ascii() {
+            return Ok(Cow::Borrowed(&self.0));
+        }
+
+        let invalid = || NameError::Idna(self.0.to_owned());
+        let encoded = Uts46::new()
+            .to_ascii(
+                self.0.as_bytes(),
+                AsciiDenyList::EMPTY,
+                Hyphens::Allow,
+                DnsLength::Ignore,
+            )
+            .map_err(|_| invalid())?;
+        if encoded.contains('.') {
+            return Err(invalid());
+        }
+
+        Ok(Cow::Owned(encoded.into_owned()))
+    }
+
+    /// The unicode form of a `xn--` label, any other label
+    /// or one which does not decode into a single valid label is returned as it is.
+    pub fn to_unicode(&self) -> Cow<'_, str> {
+        let is_ace = self
+            .0
+            .get(..ACE_PREFIX.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(ACE_PREFIX));
+        if !is_ace {
+            return Cow::Borrowed(&self.0);
+        }
+
+        let (decoded, result) =
+            Uts46::new().to_unicode(self.0.as_bytes(), AsciiDenyList::EMPTY, Hyphens::Allow);
+        match result {
+            Ok(()) if !decoded.contains('.') => Cow::Owned(decoded.into_owned()),
+            _ => Cow::Borrowed(&self.0),
+        }
+    }
+
+    fn lowercase(&self) -> impl Iterator<Item = u8> + '_ {
+        self.0.bytes().map(|b| b.to_ascii_lowercase())
+    }
+}
+
+impl PartialEq for Label {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_ignore_ascii_case(&other.0)
+    }
+}
+
+impl Eq for Label {}
+
+impl PartialOrd for Label {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Label {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.lowercase().cmp(other.lowercase())
     }
 }
 
+impl Hash for Label {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.0.len());
+        for b in self.lowercase() {
+            state.write_u8(b);
+        }
+    }
+}
+
+/// Escapes dots and backslashes with a backslash and control characters as `\DDD`,
+/// the same presentation format avahi uses, so that it parses back into the same label.
 impl fmt::Display for Label {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.as_str())
+        for c in self.0.chars() {
+            match c {
+                '.' | '\\' => write!(f, "\\{c}")?,
+                c if c.is_ascii_control() => write!(f, "\\{:03}", c as u8)?,
+                c => f.write_char(c)?,
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -50,42 +227,219 @@ impl<'a> Iterator for NameIter<'a> {
     type Item = &'a Label;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let &[0u8] = self.0 {
+        if let [] | [0, ..] = self.0 {
             None
         } else {
-            let (label, right) = Label::split_from(&self.0);
+            let (label, right) = Label::split_from(self.0);
             self.0 = right;
             Some(label)
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// A domain name in wire format, a sequence of length-prefixed labels terminated by the empty root label.
+/// Relative names like `vault.local`, written without the trailing dot,
+/// are followed by another zero byte which is not part of the wire format.
+///
+/// Every constructor validates the labels, as [`Label`] relies on them.
+/// Names are compared label by label ignoring ascii case and whether they are absolute.
+#[derive(Debug)]
+#[repr(transparent)]
 pub struct Name([u8]);
 
 impl Name {
+    fn new(wire: &[u8]) -> &Self {
+        // SAFETY: `Name` is a `repr(transparent)` wrapper of `[u8]`
+        unsafe { &*(wire as *const [u8] as *const Name) }
+    }
+
+    /// Wire format including the terminating zero byte, the same for relative and absolute names.
     pub fn as_slice(&self) -> &[u8] {
-        &self.0
+        &self.0[..self.wire_len()]
+    }
+
+    /// Wire format as sent in records, the same as [`Name::as_slice`].
+    pub fn to_wire(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.as_slice())
+    }
+
+    fn wire_len(&self) -> usize {
+        // zero bytes inside of labels are skipped, only the root label is empty
+        let mut len = 0;
+        while self.0[len] != 0 {
+            len += 1 + usize::from(self.0[len]);
+        }
+        len + 1
     }
 
-    pub fn iter(&self) -> NameIter {
+    pub fn iter(&self) -> NameIter<'_> {
         self.into_iter()
     }
 
+    pub fn label_count(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn first_label(&self) -> Option<&Label> {
+        self.iter().next()
+    }
+
+    /// The first label.
+    ///
+    /// # Panics
+    ///
+    /// Panics for the root name, which has no label.
+    #[deprecated(note = "use `first_label`, this is not the root label")]
     pub fn root(&self) -> &Label {
-        self.iter().next().unwrap()
+        self.first_label().expect("name has a label")
+    }
+
+    pub fn is_absolute(&self) -> bool {
+        self.wire_len() == self.0.len()
     }
 
+    /// Whether this is `.`, the absolute name without any label.
     pub fn is_root(&self) -> bool {
-        self.iter().count() == 1
+        self.0 == [0]
     }
 
+    /// The name without its first label,
+    /// `None` for the root and for relative names of a single label.
+    pub fn parent(&self) -> Option<&Name> {
+        self.first_label()?;
+
+        let (_, rest) = Label::split_from(&self.0);
+        (rest != RELATIVE_END).then(|| Self::new(rest))
+    }
+
+    /// The relative name of the labels in front of `domain`,
+    /// which is empty if both names are equal.
+    pub fn strip_suffix(&self, domain: impl AsRef<Name>) -> Option<NameBuf> {
+        let offset = self.suffix_offset(domain.as_ref())?;
+
+        let mut name = self.0[..offset].to_vec();
+        name.extend(RELATIVE_END);
+        Some(NameBuf(name))
+    }
+
+    /// Whether the name equals `domain` or is below it.
+    pub fn is_subdomain_of(&self, domain: impl AsRef<Name>) -> bool {
+        self.suffix_offset(domain.as_ref()).is_some()
+    }
+
+    fn suffix_offset(&self, domain: &Name) -> Option<usize> {
+        let mut offset = 0;
+
+        loop {
+            let suffix = Self::new(&self.0[offset..]);
+            if suffix == domain {
+                return Some(offset);
+            }
+
+            offset += 1 + suffix.first_label()?.0.len();
+        }
+    }
+
+    /// Whether the first labels are those of `base`, ignoring ascii case.
+    pub fn starts_with_labels(&self, base: impl AsRef<Name>) -> bool {
+        let mut labels = self.iter();
+        base.as_ref()
+            .iter()
+            .all(|label| labels.next() == Some(label))
+    }
+
+    /// Whether the wire format starts with the one of `base`,
+    /// which includes the terminating zero byte and is case-sensitive.
+    #[deprecated(note = "use `starts_with_labels` to compare labels ignoring case")]
     pub fn starts_with(&self, base: impl AsRef<Name>) -> bool {
-        self.0.starts_with(&base.as_ref().0)
+        self.as_slice().starts_with(base.as_ref().as_slice())
     }
 
+    /// Whether the wire format ends with the one of `base`,
+    /// which may match in the middle of a label and is case-sensitive.
+    #[deprecated(note = "use `is_subdomain_of` to compare whole labels ignoring case")]
     pub fn ends_with(&self, base: impl AsRef<Name>) -> bool {
-        self.0.ends_with(&base.as_ref().0)
+        self.as_slice().ends_with(base.as_ref().as_slice())
+    }
+
+    /// The name with every label in its IDNA form, see [`Label::to_ascii`].
+    ///
+    /// Fails for labels IDNA does not allow
+    /// and if a label or the whole name becomes too long by the encoding.
+    pub fn to_ascii(&self) -> Result<NameBuf, NameError> {
+        self.map_labels(Label::to_ascii)
+    }
+
+    /// The name with every `xn--` label decoded, see [`Label::to_unicode`].
+    ///
+    /// Fails if a label or the whole name becomes too long by the decoding.
+    pub fn to_unicode(&self) -> Result<NameBuf, NameError> {
+        self.map_labels(|label| Ok(label.to_unicode()))
+    }
+
+    /// The name as published with the given encoding.
+    pub fn encode(&self, encoding: NameEncoding) -> Result<NameBuf, NameError> {
+        match encoding {
+            NameEncoding::Utf8 => Ok(self.to_owned()),
+            NameEncoding::Idna => self.to_ascii(),
+        }
+    }
+
+    fn map_labels<'a>(
+        &'a self,
+        f: impl Fn(&'a Label) -> Result<Cow<'a, str>, NameError>,
+    ) -> Result<NameBuf, NameError> {
+        let mut name = NameBuf::relative();
+        for label in self {
+            name.push_raw_label(&f(label)?)?;
+        }
+        if self.is_absolute() {
+            name.make_absolute();
+        }
+
+        Ok(name)
+    }
+}
+
+impl AsRef<Name> for Name {
+    fn as_ref(&self) -> &Name {
+        self
+    }
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for Name {}
+
+impl PartialOrd for Name {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Name {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl Hash for Name {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for label in self {
+            label.hash(state);
+        }
+    }
+}
+
+impl ToOwned for Name {
+    type Owned = NameBuf;
+
+    fn to_owned(&self) -> Self::Owned {
+        NameBuf(self.0.to_vec())
     }
 }
 
@@ -98,60 +452,206 @@ impl<'a> IntoIterator for &'a Name {
     }
 }
 
+/// Escaped labels separated by dots, absolute names end with a dot.
 impl fmt::Display for Name {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut iter = self.iter();
-        let first = iter.next().unwrap();
-        write!(f, "{first}")?;
 
+        match iter.next() {
+            Some(first) => write!(f, "{first}")?,
+            None if self.is_absolute() => return f.write_str("."),
+            None => return Ok(()),
+        }
         for label in iter {
             write!(f, ".{label}")?;
         }
+        if self.is_absolute() {
+            f.write_str(".")?;
+        }
 
         Ok(())
     }
 }
 
-#[derive(
-    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, SerializeDisplay, DeserializeFromStr,
-)]
+#[derive(Debug, Clone, SerializeDisplay, DeserializeFromStr)]
 pub struct NameBuf(Vec<u8>);
 
 impl NameBuf {
-    pub fn into_vec(self) -> Vec<u8> {
+    /// The absolute name `.` without any label.
+    pub fn root() -> Self {
+        Self(vec![0])
+    }
+
+    /// The wire format, see [`Name::as_slice`].
+    pub fn into_vec(mut self) -> Vec<u8> {
+        self.0.truncate(self.wire_len());
         self.0
     }
+
+    /// The relative name without any label, to push labels onto.
+    fn relative() -> Self {
+        Self(RELATIVE_END.to_vec())
+    }
+
+    fn make_absolute(&mut self) {
+        self.0.truncate(self.wire_len());
+    }
+
+    /// Parses an uncompressed name in wire format, e.g. from the rdata of a record,
+    /// and returns it together with the remaining bytes.
+    pub fn from_wire(wire: &[u8]) -> Result<(Self, &[u8]), NameError> {
+        let mut len = 0;
+
+        loop {
+            let Some(&label_len) = wire.get(len) else {
+                return Err(NameError::Truncated);
+            };
+            let label_len = usize::from(label_len);
+
+            if label_len == 0 {
+                len += 1;
+                break;
+            }
+            // the two highest bits mark a compression pointer or a reserved label type
+            if label_len > MAX_LABEL_LEN {
+                return Err(NameError::Compressed);
+            }
+
+            let Some(label) = wire.get(len + 1..len + 1 + label_len) else {
+                return Err(NameError::Truncated);
+            };
+            if str::from_utf8(label).is_err() {
+                return Err(NameError::InvalidUtf8(label.to_vec()));
+            }
+
+            len += 1 + label_len;
+            if len + 1 > MAX_NAME_LEN {
+                return Err(NameError::LongName);
+            }
+        }
+
+        let (name, rest) = wire.split_at(len);
+        Ok((Self(name.to_vec()), rest))
+    }
+
+    /// Appends a label, e.g. `local` to `vault`.
+    /// The label is escaped like in [`NameBuf::from_str`], so a literal dot is written as `\.`.
+    pub fn push_label(&mut self, label: &str) -> Result<(), NameError> {
+        self.push_raw_label(&unescape_label(label)?)
+    }
+
+    /// Prepends an escaped label, e.g. `vault` to `local`.
+    pub fn prepend_label(&mut self, label: &str) -> Result<(), NameError> {
+        self.prepend_raw_label(&unescape_label(label)?)
+    }
+
+    /// Appends a label as it is, which may contain any character including dots.
+    pub fn push_raw_label(&mut self, label: &str) -> Result<(), NameError> {
+        let end = self.wire_len() - 1;
+        self.insert_label(end, label)
+    }
+
+    /// Prepends a label as it is, e.g. the instance name of a service.
+    pub fn prepend_raw_label(&mut self, label: &str) -> Result<(), NameError> {
+        self.insert_label(0, label)
+    }
+
+    /// Appends the unescaped bytes of a label and clears them for the next one.
+    fn push_escaped_label(&mut self, label: &mut Vec<u8>) -> Result<(), NameError> {
+        let label = String::from_utf8(mem::take(label))
+            .map_err(|e| NameError::InvalidUtf8(e.into_bytes()))?;
+        self.push_raw_label(&label)
+    }
+
+    fn insert_label(&mut self, at: usize, label: &str) -> Result<(), NameError> {
+        Label::validate(label)?;
+        if self.wire_len() + 1 + label.len() > MAX_NAME_LEN {
+            return Err(NameError::LongName);
+        }
+
+        let encoded = [label.len() as u8].into_iter().chain(label.bytes());
+        self.0.splice(at..at, encoded);
+        Ok(())
+    }
 }
 
+/// Accepts relative names and absolute names with a trailing dot.
+/// Within a label `\.` is a literal dot, `\DDD` the byte of the decimal value
+/// and a backslash followed by any other character that character.
 impl FromStr for NameBuf {
     type Err = NameError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let len = s.len() + 1;
-        if len >= 255 {
-            return Err(NameError::LongName);
+        if s == "." {
+            return Ok(Self::root());
         }
 
-        let mut buf = Vec::with_capacity(len);
-        for label in s.split('.') {
-            if label.is_empty() {
-                return Err(NameError::EmptyLabel);
-            }
-            if label.len() >= 64 {
-                return Err(NameError::LongLabel);
+        let mut name = Self::relative();
+        let mut label = Vec::new();
+        let mut absolute = false;
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '.' if chars.as_str().is_empty() => absolute = true,
+                '.' => name.push_escaped_label(&mut label)?,
+                '\\' => unescape(&mut chars, &mut label)
+                    .ok_or_else(|| NameError::Escape(s.to_owned()))?,
+                c => label.extend(c.encode_utf8(&mut [0; 4]).bytes()),
             }
+        }
+        name.push_escaped_label(&mut label)?;
+        if absolute {
+            name.make_absolute();
+        }
 
-            buf.push(label.len() as u8);
-            buf.extend(label.as_bytes());
+        Ok(name)
+    }
+}
+
+/// Decodes the escape sequence following a backslash into the bytes of a label,
+/// `None` if it is incomplete or `\DDD` exceeds a byte.
+fn unescape(chars: &mut str::Chars, label: &mut Vec<u8>) -> Option<()> {
+    match chars.next()? {
+        d if d.is_ascii_digit() => {
+            let value = [Some(d), chars.next(), chars.next()]
+                .into_iter()
+                .try_fold(0u32, |value, d| Some(value * 10 + d?.to_digit(10)?))?;
+            label.push(u8::try_from(value).ok()?);
         }
-        buf.push(0u8);
-        Ok(Self(buf))
+        c => label.extend(c.encode_utf8(&mut [0; 4]).bytes()),
     }
+
+    Some(())
+}
+
+/// Decodes a single escaped label, which must not contain unescaped dots.
+fn unescape_label(s: &str) -> Result<String, NameError> {
+    let mut label = Vec::new();
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => return Err(NameError::Dot(s.to_owned())),
+            '\\' => {
+                unescape(&mut chars, &mut label).ok_or_else(|| NameError::Escape(s.to_owned()))?
+            }
+            c => label.extend(c.encode_utf8(&mut [0; 4]).bytes()),
+        }
+    }
+
+    String::from_utf8(label).map_err(|e| NameError::InvalidUtf8(e.into_bytes()))
 }
 
 impl AsRef<Name> for NameBuf {
     fn as_ref(&self) -> &Name {
-        unsafe { &*(self.0.as_slice() as *const [u8] as *const Name) }
+        Name::new(&self.0)
+    }
+}
+
+impl Borrow<Name> for NameBuf {
+    fn borrow(&self) -> &Name {
+        self.as_ref()
     }
 }
 
@@ -163,6 +663,32 @@ impl Deref for NameBuf {
     }
 }
 
+impl PartialEq for NameBuf {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_ref() == other.as_ref()
+    }
+}
+
+impl Eq for NameBuf {}
+
+impl PartialOrd for NameBuf {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NameBuf {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_ref().cmp(other.as_ref())
+    }
+}
+
+impl Hash for NameBuf {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_ref().hash(state)
+    }
+}
+
 impl fmt::Display for NameBuf {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.as_ref().fmt(f)
@@ -173,7 +699,7 @@ impl fmt::Display for NameBuf {
 mod tests {
     use std::str::FromStr;
 
-    use super::NameBuf;
+    use super::{NameBuf, NameEncoding, NameError};
 
     #[test]
     fn name() {
@@ -181,8 +707,291 @@ mod tests {
         let name = NameBuf::from_str(domain).unwrap();
 
         assert_eq!(
-            name.as_slice(),
+            name.to_wire().as_ref(),
             &[1, b'a', 5, b'l', b'o', b'c', b'a', b'l', 0]
         )
     }
+
+    #[test]
+    fn from_wire() {
+        let wire = [1, b'a', 5, b'l', b'o', b'c', b'a', b'l', 0, 0xff];
+        let (name, rest) = NameBuf::from_wire(&wire).unwrap();
+        assert_eq!(name.to_string(), "a.local.");
+        assert_eq!(rest, [0xff]);
+
+        let (name, rest) = NameBuf::from_wire(&[0]).unwrap();
+        assert!(name.is_root() && rest.is_empty());
+        assert_eq!(name.to_string(), ".");
+
+        let (name, _) = NameBuf::from_wire(&[3, b'a', b'.', b'b', 0]).unwrap();
+        assert_eq!(name.label_count(), 1);
+        assert_eq!(name.to_string(), "a\\.b.");
+
+        let cases: [(&[u8], NameError); 7] = [
+            (&[], NameError::Truncated),
+            (&[1, b'a'], NameError::Truncated),
+            (&[5, b'a', 0], NameError::Truncated),
+            (&[1, b'a', 0xc0, 0x0c], NameError::Compressed),
+            (&[0x40, b'a', 0], NameError::Compressed),
+            (
+                &[2, 0xff, 0xfe, 0],
+                NameError::InvalidUtf8(vec![0xff, 0xfe]),
+            ),
+            (&[2, b'a', b'b', 0x3f], NameError::Truncated),
+        ];
+        for (wire, error) in cases {
+            assert_eq!(NameBuf::from_wire(wire), Err(error), "{wire:?}");
+        }
+
+        let mut long = Vec::new();
+        for _ in 0..4 {
+            long.push(63);
+            long.extend([b'a'; 63]);
+        }
+        long.push(0);
+        assert_eq!(NameBuf::from_wire(&long), Err(NameError::LongName));
+        long.drain(1..3);
+        long[0] = 61;
+        assert!(NameBuf::from_wire(&long).is_ok());
+    }
+
+    /// Deterministic xorshift, so that failures can be reproduced.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    /// Any accepted input has to survive every accessor and round trip through its text form.
+    fn check(wire: &[u8]) {
+        let Ok((name, rest)) = NameBuf::from_wire(wire) else {
+            return;
+        };
+
+        assert_eq!([name.as_slice(), rest].concat(), wire);
+        assert!(name.is_absolute());
+        assert_eq!(name.to_wire(), name.as_slice());
+        assert!(name.iter().all(|label| !label.as_str().is_empty()));
+        assert_eq!(NameBuf::from_str(&name.to_string()), Ok(name));
+    }
+
+    #[test]
+    fn from_wire_fuzz() {
+        let mut rng = Rng(0x5eed_1e55_ba5e_ba11);
+        let seeds = [
+            NameBuf::from_str("vault.local").unwrap().into_vec(),
+            NameBuf::from_str("_http._tcp.local").unwrap().into_vec(),
+            "Ünïcödé.local".parse::<NameBuf>().unwrap().into_vec(),
+        ];
+
+        for _ in 0..20_000 {
+            let len = rng.below(300);
+            let random = (0..len).map(|_| rng.next() as u8).collect::<Vec<_>>();
+            check(&random);
+
+            let mut mutated = seeds[rng.below(seeds.len())].clone();
+            for _ in 0..=rng.below(4) {
+                let i = rng.below(mutated.len());
+                match rng.below(3) {
+                    0 => mutated[i] = rng.next() as u8,
+                    1 => mutated.truncate(i),
+                    _ => mutated.insert(i, rng.next() as u8),
+                }
+                if mutated.is_empty() {
+                    break;
+                }
+            }
+            check(&mutated);
+        }
+    }
+
+    #[test]
+    fn absolute() {
+        let relative = NameBuf::from_str("vault.local").unwrap();
+        let absolute = NameBuf::from_str("vault.local.").unwrap();
+
+        assert!(!relative.is_absolute());
+        assert!(absolute.is_absolute());
+        assert_eq!(relative, absolute);
+        assert_eq!(relative.to_wire(), absolute.as_slice());
+        assert_eq!(relative.as_slice(), absolute.as_slice());
+        assert_eq!(relative.clone().into_vec(), absolute.clone().into_vec());
+        assert_eq!(absolute.to_string(), "vault.local.");
+        assert_eq!(NameBuf::from_str("."), Ok(NameBuf::root()));
+        assert_eq!(NameBuf::from_str(""), Err(NameError::EmptyLabel));
+        assert_eq!(NameBuf::from_str("a.."), Err(NameError::EmptyLabel));
+
+        // a trailing zero byte of the last label does not make a name absolute
+        let nul = NameBuf::from_str("a\0").unwrap();
+        assert!(!nul.is_absolute());
+        assert_eq!(nul.label_count(), 1);
+    }
+
+    #[test]
+    fn hierarchy() {
+        let name = NameBuf::from_str("Web._HTTP._tcp.local").unwrap();
+        let domain = NameBuf::from_str("_http._TCP.local.").unwrap();
+
+        assert_eq!(name.label_count(), 4);
+        assert_eq!(name.first_label().unwrap().as_str(), "Web");
+        assert_eq!(name.parent().unwrap(), domain.as_ref());
+        assert_eq!(name.parent().unwrap().to_string(), "_HTTP._tcp.local");
+        assert_eq!(NameBuf::from_str("local").unwrap().parent(), None);
+        assert!(NameBuf::from_str("local.")
+            .unwrap()
+            .parent()
+            .unwrap()
+            .is_root());
+
+        assert_eq!(name.strip_suffix(&domain).unwrap().to_string(), "Web");
+        assert_eq!(name.strip_suffix(&name).unwrap().label_count(), 0);
+        assert_eq!(name.strip_suffix(NameBuf::root()), Some(name.clone()));
+        assert!(name.is_subdomain_of(&domain));
+        assert!(!domain.is_subdomain_of(&name));
+
+        // suffixes and prefixes only match whole labels
+        let partial = NameBuf::from_str("tcp.local").unwrap();
+        assert!(!name.is_subdomain_of(&partial));
+        assert!(name.starts_with_labels(NameBuf::from_str("web._http").unwrap()));
+        assert!(!name.starts_with_labels(NameBuf::from_str("we").unwrap()));
+
+        let mut built = NameBuf::from_str("_tcp.local.").unwrap();
+        built.prepend_label("_http").unwrap();
+        built.prepend_label("web").unwrap();
+        assert_eq!(built, name);
+        assert_eq!(built.to_string(), "web._http._tcp.local.");
+
+        let mut built = NameBuf::from_str("vault").unwrap();
+        built.push_label("local").unwrap();
+        assert_eq!(built.to_string(), "vault.local");
+        assert_eq!(
+            built.push_label("a.b"),
+            Err(NameError::Dot("a.b".to_owned()))
+        );
+        assert_eq!(built.push_label(""), Err(NameError::EmptyLabel));
+        assert_eq!(built.push_label(&"a".repeat(64)), Err(NameError::LongLabel));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated() {
+        let name = NameBuf::from_str("web._http._tcp.local").unwrap();
+
+        assert_eq!(name.root().as_str(), "web");
+        assert!(name.ends_with(NameBuf::from_str("_tcp.local.").unwrap()));
+        assert!(!name.ends_with(NameBuf::from_str("_TCP.local").unwrap()));
+        assert!(name.starts_with(&name));
+        assert!(!name.starts_with(NameBuf::from_str("web").unwrap()));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        use std::collections::HashSet;
+
+        let names = ["Vault.Local", "vault.local", "VAULT.LOCAL."]
+            .map(|name| NameBuf::from_str(name).unwrap());
+        assert!(names.iter().all(|name| *name == names[0]));
+        assert_eq!(names.iter().collect::<HashSet<_>>().len(), 1);
+        // but the original spelling is kept
+        assert_eq!(names[0].to_string(), "Vault.Local");
+    }
+
+    #[test]
+    fn escapes() {
+        let name = NameBuf::from_str(r"Living\.Room\\1\0092.local").unwrap();
+        assert_eq!(name.label_count(), 2);
+        assert_eq!(name.first_label().unwrap().as_str(), "Living.Room\\1\t2");
+        assert_eq!(name.to_string(), r"Living\.Room\\1\0092.local");
+
+        let mut built = NameBuf::from_str("local").unwrap();
+        built.prepend_label(r"a\.b").unwrap();
+        assert_eq!(built.to_string(), r"a\.b.local");
+        assert_eq!(NameBuf::from_str(&built.to_string()), Ok(built.clone()));
+        built.prepend_raw_label("Living Room v1.2").unwrap();
+        assert_eq!(built.to_string(), r"Living Room v1\.2.a\.b.local");
+        assert_eq!(built.first_label().unwrap().as_str(), "Living Room v1.2");
+        assert_eq!(
+            built.push_label(r"c\"),
+            Err(NameError::Escape(r"c\".to_owned()))
+        );
+        assert_eq!(
+            built.prepend_label(r"a.\.b"),
+            Err(NameError::Dot(r"a.\.b".to_owned()))
+        );
+
+        // escapes of other characters only keep them, even a trailing dot
+        assert_eq!(NameBuf::from_str(r"\a\ b"), NameBuf::from_str("a b"));
+        let dot = NameBuf::from_str(r"a\.").unwrap();
+        assert!(!dot.is_absolute());
+        assert_eq!(dot.first_label().unwrap().as_str(), "a.");
+
+        for invalid in [r"a\", r"a\25", r"a\256", r"a\2x5"] {
+            assert_eq!(
+                NameBuf::from_str(invalid),
+                Err(NameError::Escape(invalid.to_owned()))
+            );
+        }
+        assert_eq!(
+            NameBuf::from_str(r"\255.local"),
+            Err(NameError::InvalidUtf8(vec![0xff]))
+        );
+        // escaped utf-8 bytes are joined into a character
+        assert_eq!(
+            NameBuf::from_str(r"k\195\188che.local")
+                .unwrap()
+                .to_string(),
+            "küche.local"
+        );
+    }
+
+    #[test]
+    fn idna() {
+        let kitchen = NameBuf::from_str("Küche.local.").unwrap();
+        assert_eq!(kitchen.to_string(), "Küche.local.");
+        assert_eq!(kitchen.first_label().unwrap().as_str().len(), 6);
+
+        let ascii = kitchen.to_ascii().unwrap();
+        assert_eq!(ascii.to_string(), "xn--kche-0ra.local.");
+        assert_eq!(ascii.to_unicode().unwrap().to_string(), "küche.local.");
+        assert_eq!(kitchen.encode(NameEncoding::Utf8), Ok(kitchen.clone()));
+        assert_eq!(kitchen.encode(NameEncoding::Idna), Ok(ascii));
+
+        let japanese = NameBuf::from_str("台所のプリンター.local").unwrap();
+        let ascii = japanese.to_ascii().unwrap();
+        assert!(ascii.to_string().is_ascii());
+        assert_eq!(ascii.to_unicode(), Ok(japanese));
+
+        // ascii names and invalid punycode are kept as they are
+        let plain = NameBuf::from_str("Vault.xn--zz.local").unwrap();
+        assert_eq!(plain.to_ascii().unwrap().to_string(), "Vault.xn--zz.local");
+        assert_eq!(
+            plain.to_unicode().unwrap().to_string(),
+            "Vault.xn--zz.local"
+        );
+
+        // labels are normalized, so decomposed umlauts are published the same
+        let decomposed = NameBuf::from_str("Ku\u{308}che.local.").unwrap();
+        assert_eq!(decomposed.to_ascii().unwrap(), kitchen.to_ascii().unwrap());
+        assert_eq!(
+            NameBuf::from_str("a\u{3002}b.local").unwrap().to_ascii(),
+            Err(NameError::Idna("a\u{3002}b".to_owned()))
+        );
+        assert_eq!(
+            NameBuf::from_str("ü\u{fffd}.local").unwrap().to_ascii(),
+            Err(NameError::Idna("ü\u{fffd}".to_owned()))
+        );
+
+        // punycode is longer than utf-8 for widely spread code points
+        let long = NameBuf::from_str("ü😀ж台€ñ🎉ß漢ю🐈ä字йΩ🚀çø界éλ").unwrap();
+        assert_eq!(long.to_ascii(), Err(NameError::LongLabel));
+    }
 }
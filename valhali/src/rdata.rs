@@ -1,15 +1,39 @@
 use core::fmt;
-use std::str::FromStr;
+use std::{
+    borrow::Cow,
+    net::{AddrParseError, Ipv4Addr, Ipv6Addr},
+    str::{self, FromStr},
+};
 
 use avahi_zbus::DnsType;
 use serde::{Deserialize, Serialize};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use thiserror::Error;
 
-use crate::name::{Name, NameBuf, NameError};
+use crate::{
+    name::{Name, NameBuf, NameError},
+    txt::TxtRecord,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RdataError {
+    #[error("Record data ends unexpectedly")]
+    Truncated,
+    #[error("Record data has {0} trailing bytes")]
+    Trailing(usize),
+    #[error("Character string is not valid utf-8")]
+    InvalidString,
+    #[error("Character string {0:?} has more than 255 bytes")]
+    LongString(String),
+    #[error(transparent)]
+    Name(#[from] NameError),
+}
 
 pub trait RecordData {
     const KIND: DnsType;
 
-    fn as_rdata(&self) -> &[u8];
+    /// Wire format of the data, borrowed if it is stored as such.
+    fn to_rdata(&self) -> Cow<'_, [u8]>;
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -21,6 +45,16 @@ impl Cname {
     }
 }
 
+impl Cname {
+    pub fn decode(rdata: &[u8]) -> Result<Self, RdataError> {
+        let mut reader = Reader(rdata);
+        let name = reader.name()?;
+        reader.finish()?;
+
+        Ok(Self(name))
+    }
+}
+
 impl From<NameBuf> for Cname {
     fn from(value: NameBuf) -> Self {
         Self(value)
@@ -46,15 +80,591 @@ impl fmt::Display for Cname {
 impl RecordData for Cname {
     const KIND: DnsType = DnsType::CNAME;
 
-    fn as_rdata(&self) -> &[u8] {
-        self.0.as_slice()
+    fn to_rdata(&self) -> Cow<'_, [u8]> {
+        self.0.to_wire()
     }
 }
 
 impl RecordData for &Cname {
     const KIND: DnsType = DnsType::CNAME;
 
-    fn as_rdata(&self) -> &[u8] {
-        self.0.as_slice()
+    fn to_rdata(&self) -> Cow<'_, [u8]> {
+        self.0.to_wire()
+    }
+}
+
+/// IPv4 host address, see [RFC 1035](https://datatracker.ietf.org/doc/html/rfc1035#section-3.4.1).
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, SerializeDisplay, DeserializeFromStr,
+)]
+pub struct A([u8; 4]);
+
+impl A {
+    pub fn as_addr(&self) -> Ipv4Addr {
+        Ipv4Addr::from(self.0)
+    }
+
+    pub fn decode(rdata: &[u8]) -> Result<Self, RdataError> {
+        let mut reader = Reader(rdata);
+        let octets = reader.array()?;
+        reader.finish()?;
+
+        Ok(Self(octets))
+    }
+}
+
+impl From<Ipv4Addr> for A {
+    fn from(value: Ipv4Addr) -> Self {
+        Self(value.octets())
+    }
+}
+
+impl FromStr for A {
+    type Err = AddrParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let addr = Ipv4Addr::from_str(s)?;
+
+        Ok(Self::from(addr))
+    }
+}
+
+impl fmt::Display for A {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_addr().fmt(f)
+    }
+}
+
+impl RecordData for A {
+    const KIND: DnsType = DnsType::A;
+
+    fn to_rdata(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.0)
+    }
+}
+
+/// IPv6 host address, see [RFC 3596](https://datatracker.ietf.org/doc/html/rfc3596#section-2.2).
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, SerializeDisplay, DeserializeFromStr,
+)]
+pub struct Aaaa([u8; 16]);
+
+impl Aaaa {
+    pub fn as_addr(&self) -> Ipv6Addr {
+        Ipv6Addr::from(self.0)
+    }
+
+    pub fn decode(rdata: &[u8]) -> Result<Self, RdataError> {
+        let mut reader = Reader(rdata);
+        let octets = reader.array()?;
+        reader.finish()?;
+
+        Ok(Self(octets))
+    }
+}
+
+impl From<Ipv6Addr> for Aaaa {
+    fn from(value: Ipv6Addr) -> Self {
+        Self(value.octets())
+    }
+}
+
+impl FromStr for Aaaa {
+    type Err = AddrParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let addr = Ipv6Addr::from_str(s)?;
+
+        Ok(Self::from(addr))
+    }
+}
+
+impl fmt::Display for Aaaa {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_addr().fmt(f)
+    }
+}
+
+impl RecordData for Aaaa {
+    const KIND: DnsType = DnsType::AAAA;
+
+    fn to_rdata(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.0)
+    }
+}
+
+/// Domain name pointer, see [RFC 1035](https://datatracker.ietf.org/doc/html/rfc1035#section-3.3.12).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ptr(pub NameBuf);
+
+impl Ptr {
+    pub fn decode(rdata: &[u8]) -> Result<Self, RdataError> {
+        let mut reader = Reader(rdata);
+        let name = reader.name()?;
+        reader.finish()?;
+
+        Ok(Self(name))
+    }
+}
+
+impl fmt::Display for Ptr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl RecordData for Ptr {
+    const KIND: DnsType = DnsType::PTR;
+
+    fn to_rdata(&self) -> Cow<'_, [u8]> {
+        self.0.to_wire()
+    }
+}
+
+/// Authoritative name server, see [RFC 1035](https://datatracker.ietf.org/doc/html/rfc1035#section-3.3.11).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ns(pub NameBuf);
+
+impl Ns {
+    pub fn decode(rdata: &[u8]) -> Result<Self, RdataError> {
+        let mut reader = Reader(rdata);
+        let name = reader.name()?;
+        reader.finish()?;
+
+        Ok(Self(name))
+    }
+}
+
+impl fmt::Display for Ns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Mail exchange, see [RFC 1035](https://datatracker.ietf.org/doc/html/rfc1035#section-3.3.9).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Mx {
+    pub preference: u16,
+    pub exchange: NameBuf,
+}
+
+impl Mx {
+    pub fn decode(rdata: &[u8]) -> Result<Self, RdataError> {
+        let mut reader = Reader(rdata);
+        let preference = reader.u16()?;
+        let exchange = reader.name()?;
+        reader.finish()?;
+
+        Ok(Self {
+            preference,
+            exchange,
+        })
+    }
+}
+
+impl fmt::Display for Mx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.preference, self.exchange)
+    }
+}
+
+/// Service location, see [RFC 2782](https://datatracker.ietf.org/doc/html/rfc2782).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Srv {
+    #[serde(default)]
+    pub priority: u16,
+    #[serde(default)]
+    pub weight: u16,
+    pub port: u16,
+    pub target: NameBuf,
+}
+
+impl Srv {
+    pub fn decode(rdata: &[u8]) -> Result<Self, RdataError> {
+        let mut reader = Reader(rdata);
+        let priority = reader.u16()?;
+        let weight = reader.u16()?;
+        let port = reader.u16()?;
+        let target = reader.name()?;
+        reader.finish()?;
+
+        Ok(Self {
+            priority,
+            weight,
+            port,
+            target,
+        })
+    }
+}
+
+impl fmt::Display for Srv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            priority,
+            weight,
+            port,
+            target,
+        } = self;
+
+        write!(f, "{priority} {weight} {port} {target}")
+    }
+}
+
+impl RecordData for Srv {
+    const KIND: DnsType = DnsType::SRV;
+
+    fn to_rdata(&self) -> Cow<'_, [u8]> {
+        let mut rdata = Vec::with_capacity(6 + self.target.as_slice().len());
+        rdata.extend(self.priority.to_be_bytes());
+        rdata.extend(self.weight.to_be_bytes());
+        rdata.extend(self.port.to_be_bytes());
+        rdata.extend(self.target.to_wire().as_ref());
+
+        Cow::Owned(rdata)
+    }
+}
+
+/// Host information, see [RFC 1035](https://datatracker.ietf.org/doc/html/rfc1035#section-3.3.2).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "HinfoRepr", into = "HinfoRepr")]
+pub struct Hinfo {
+    cpu: String,
+    os: String,
+}
+
+impl Hinfo {
+    /// Both strings are sent as character strings of at most 255 bytes.
+    pub fn new(cpu: impl Into<String>, os: impl Into<String>) -> Result<Self, RdataError> {
+        let (cpu, os) = (cpu.into(), os.into());
+
+        for string in [&cpu, &os] {
+            if string.len() > 255 {
+                return Err(RdataError::LongString(string.clone()));
+            }
+        }
+
+        Ok(Self { cpu, os })
+    }
+
+    pub fn cpu(&self) -> &str {
+        &self.cpu
+    }
+
+    pub fn os(&self) -> &str {
+        &self.os
+    }
+
+    pub fn decode(rdata: &[u8]) -> Result<Self, RdataError> {
+        let mut reader = Reader(rdata);
+        let cpu = reader.string()?;
+        let os = reader.string()?;
+        reader.finish()?;
+
+        Ok(Self { cpu, os })
+    }
+}
+
+impl fmt::Display for Hinfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} {:?}", self.cpu, self.os)
+    }
+}
+
+impl RecordData for Hinfo {
+    const KIND: DnsType = DnsType::HINFO;
+
+    fn to_rdata(&self) -> Cow<'_, [u8]> {
+        let mut rdata = Vec::with_capacity(2 + self.cpu.len() + self.os.len());
+        for string in [&self.cpu, &self.os] {
+            rdata.push(string.len() as u8);
+            rdata.extend(string.as_bytes());
+        }
+
+        Cow::Owned(rdata)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HinfoRepr {
+    cpu: String,
+    os: String,
+}
+
+impl TryFrom<HinfoRepr> for Hinfo {
+    type Error = RdataError;
+
+    fn try_from(HinfoRepr { cpu, os }: HinfoRepr) -> Result<Self, Self::Error> {
+        Self::new(cpu, os)
+    }
+}
+
+impl From<Hinfo> for HinfoRepr {
+    fn from(Hinfo { cpu, os }: Hinfo) -> Self {
+        Self { cpu, os }
+    }
+}
+
+/// Decodes the character strings of a TXT record,
+/// see [RFC 1035](https://datatracker.ietf.org/doc/html/rfc1035#section-3.3.14).
+pub fn decode_txt(rdata: &[u8]) -> Result<TxtRecord, RdataError> {
+    let mut reader = Reader(rdata);
+    let mut entries = Vec::new();
+
+    while !reader.0.is_empty() {
+        let len = reader.u8()?;
+        let entry = reader.bytes(len.into())?;
+        // a record without any entry is sent as a single empty string
+        if !entry.is_empty() {
+            entries.push(entry.to_vec());
+        }
+    }
+
+    Ok(TxtRecord::decode(&entries))
+}
+
+impl RecordData for TxtRecord {
+    const KIND: DnsType = DnsType::TXT;
+
+    fn to_rdata(&self) -> Cow<'_, [u8]> {
+        // a record without any entry is sent as a single empty string
+        if self.is_empty() {
+            return Cow::Borrowed(&[0]);
+        }
+
+        let mut rdata = Vec::with_capacity(self.len());
+        for entry in self {
+            rdata.push(entry.len() as u8);
+            rdata.extend(entry.encode());
+        }
+
+        Cow::Owned(rdata)
+    }
+}
+
+/// Record data of any type as received from the network,
+/// e.g. by the signals of a `RecordBrowserProxy`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rdata {
+    A(A),
+    Aaaa(Aaaa),
+    Cname(Cname),
+    Ptr(Ptr),
+    Txt(TxtRecord),
+    Srv(Srv),
+    Hinfo(Hinfo),
+    Mx(Mx),
+    Ns(Ns),
+    /// Any other record type, kept as is.
+    Unknown(DnsType, Vec<u8>),
+}
+
+impl Rdata {
+    pub fn decode(kind: DnsType, rdata: &[u8]) -> Result<Self, RdataError> {
+        let data = match kind {
+            DnsType::A => Self::A(A::decode(rdata)?),
+            DnsType::AAAA => Self::Aaaa(Aaaa::decode(rdata)?),
+            DnsType::CNAME => Self::Cname(Cname::decode(rdata)?),
+            DnsType::PTR => Self::Ptr(Ptr::decode(rdata)?),
+            DnsType::TXT => Self::Txt(decode_txt(rdata)?),
+            DnsType::SRV => Self::Srv(Srv::decode(rdata)?),
+            DnsType::HINFO => Self::Hinfo(Hinfo::decode(rdata)?),
+            DnsType::MX => Self::Mx(Mx::decode(rdata)?),
+            DnsType::NS => Self::Ns(Ns::decode(rdata)?),
+            _ => Self::Unknown(kind, rdata.to_vec()),
+        };
+
+        Ok(data)
+    }
+
+    pub fn kind(&self) -> DnsType {
+        match self {
+            Self::A(_) => DnsType::A,
+            Self::Aaaa(_) => DnsType::AAAA,
+            Self::Cname(_) => DnsType::CNAME,
+            Self::Ptr(_) => DnsType::PTR,
+            Self::Txt(_) => DnsType::TXT,
+            Self::Srv(_) => DnsType::SRV,
+            Self::Hinfo(_) => DnsType::HINFO,
+            Self::Mx(_) => DnsType::MX,
+            Self::Ns(_) => DnsType::NS,
+            Self::Unknown(kind, _) => *kind,
+        }
+    }
+}
+
+/// Presentation format of the data, unknown records use the generic
+/// encoding of [RFC 3597](https://datatracker.ietf.org/doc/html/rfc3597#section-5).
+impl fmt::Display for Rdata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::A(data) => data.fmt(f),
+            Self::Aaaa(data) => data.fmt(f),
+            Self::Cname(data) => data.fmt(f),
+            Self::Ptr(data) => data.fmt(f),
+            Self::Txt(data) => data.fmt(f),
+            Self::Srv(data) => data.fmt(f),
+            Self::Hinfo(data) => data.fmt(f),
+            Self::Mx(data) => data.fmt(f),
+            Self::Ns(data) => data.fmt(f),
+            Self::Unknown(_, bytes) => {
+                write!(f, "\\# {}", bytes.len())?;
+                if !bytes.is_empty() {
+                    f.write_str(" ")?;
+                }
+                for b in bytes {
+                    write!(f, "{b:02x}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], RdataError> {
+        if self.0.len() < len {
+            return Err(RdataError::Truncated);
+        }
+
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], RdataError> {
+        let bytes = self.bytes(N)?;
+        Ok(bytes.try_into().expect("slice has length N"))
+    }
+
+    fn u8(&mut self) -> Result<u8, RdataError> {
+        self.array().map(u8::from_be_bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, RdataError> {
+        self.array().map(u16::from_be_bytes)
+    }
+
+    fn string(&mut self) -> Result<String, RdataError> {
+        let len = self.u8()?;
+        let bytes = self.bytes(len.into())?;
+
+        str::from_utf8(bytes)
+            .map(ToOwned::to_owned)
+            .map_err(|_| RdataError::InvalidString)
+    }
+
+    fn name(&mut self) -> Result<NameBuf, RdataError> {
+        let (name, rest) = NameBuf::from_wire(self.0).map_err(|e| match e {
+            NameError::Truncated => RdataError::Truncated,
+            e => RdataError::Name(e),
+        })?;
+        self.0 = rest;
+
+        Ok(name)
+    }
+
+    fn finish(self) -> Result<(), RdataError> {
+        match self.0.len() {
+            0 => Ok(()),
+            len => Err(RdataError::Trailing(len)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use avahi_zbus::DnsType;
+
+    use std::str::FromStr;
+
+    use crate::{
+        name::{NameBuf, NameError},
+        txt::TxtRecord,
+    };
+
+    use super::{decode_txt, Hinfo, Rdata, RdataError, RecordData, Srv};
+
+    #[test]
+    fn decode() {
+        let srv = [
+            0, 1, 0, 2, 1, 187, 5, b'v', b'a', b'u', b'l', b't', 5, b'l', b'o', b'c', b'a', b'l', 0,
+        ];
+        let data = Rdata::decode(DnsType::SRV, &srv).unwrap();
+        assert_eq!(data.to_string(), "1 2 443 vault.local.");
+
+        let txt = [
+            6, b'p', b'a', b't', b'h', b'=', b'/', 4, b'f', b'l', b'a', b'g',
+        ];
+        let data = Rdata::decode(DnsType::TXT, &txt).unwrap();
+        assert_eq!(data.to_string(), "path=/ flag");
+        assert_eq!(Rdata::decode(DnsType::TXT, &[0]).unwrap().to_string(), "");
+
+        let hinfo = [3, b'x', b'8', b'6', 5, b'L', b'i', b'n', b'u', b'x'];
+        let data = Rdata::decode(DnsType::HINFO, &hinfo).unwrap();
+        assert_eq!(data.to_string(), r#""x86" "Linux""#);
+
+        let data = Rdata::decode(
+            DnsType::AAAA,
+            &[0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+        );
+        assert_eq!(data.unwrap().to_string(), "fe80::1");
+
+        let data = Rdata::decode(DnsType::SOA, &[0xde, 0xad]).unwrap();
+        assert_eq!(data.kind(), DnsType::SOA);
+        assert_eq!(data.to_string(), r"\# 2 dead");
+    }
+
+    #[test]
+    fn encode() {
+        let srv = Srv {
+            priority: 10,
+            weight: 5,
+            port: 25565,
+            target: NameBuf::from_str("mc.local").unwrap(),
+        };
+        assert_eq!(Srv::decode(&srv.to_rdata()), Ok(srv));
+
+        let hinfo = Hinfo::new("ARM", "Linux").unwrap();
+        assert_eq!(Hinfo::decode(&hinfo.to_rdata()), Ok(hinfo));
+        assert_eq!(
+            Hinfo::new("x".repeat(256), "Linux"),
+            Err(RdataError::LongString("x".repeat(256)))
+        );
+
+        let txt: TxtRecord = toml::from_str("path = \"/\"\nflag = true").unwrap();
+        assert_eq!(decode_txt(&txt.to_rdata()), Ok(txt));
+        assert_eq!(TxtRecord::new().to_rdata().as_ref(), [0]);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            Rdata::decode(DnsType::A, &[127, 0, 0]),
+            Err(RdataError::Truncated)
+        );
+        assert_eq!(
+            Rdata::decode(DnsType::A, &[127, 0, 0, 1, 0]),
+            Err(RdataError::Trailing(1))
+        );
+        assert_eq!(
+            Rdata::decode(DnsType::PTR, &[1, b'a', 0xc0, 12]),
+            Err(RdataError::Name(NameError::Compressed))
+        );
+        assert_eq!(
+            Rdata::decode(DnsType::CNAME, &[2, 0xc3, b'a', 0]),
+            Err(RdataError::Name(NameError::InvalidUtf8(vec![0xc3, b'a'])))
+        );
+        assert_eq!(
+            Rdata::decode(DnsType::MX, &[0, 10, 5, b'l', b'o']),
+            Err(RdataError::Truncated)
+        );
+        assert_eq!(
+            Rdata::decode(DnsType::TXT, &[4, b'a']),
+            Err(RdataError::Truncated)
+        );
     }
 }
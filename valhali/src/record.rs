@@ -1,4 +1,4 @@
-use avahi_zbus::Ttl;
+use avahi_zbus::{PublishFlags, Ttl};
 use serde::{Deserialize, Serialize};
 
 use crate::{name::NameBuf, rdata::RecordData};
@@ -8,10 +8,21 @@ pub struct Record<D: RecordData> {
     pub name: NameBuf,
     pub ttl: Ttl,
     pub data: D,
+    pub flags: PublishFlags,
 }
 
 impl<D: RecordData> Record<D> {
     pub fn new(name: NameBuf, ttl: Ttl, data: D) -> Self {
-        Self { name, ttl, data }
+        Self {
+            name,
+            ttl,
+            data,
+            flags: PublishFlags::empty(),
+        }
+    }
+
+    pub fn with_flags(mut self, flags: PublishFlags) -> Self {
+        self.flags = flags;
+        self
     }
 }
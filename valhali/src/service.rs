@@ -1,10 +1,16 @@
 use core::fmt;
 use std::str::FromStr;
 
+use avahi_zbus::{PublishFlags, Ttl};
 use serde::{Deserialize, Serialize};
 use serde_with::{DeserializeFromStr, SerializeDisplay};
 use thiserror::Error;
 
+use crate::{
+    name::{NameBuf, NameError},
+    txt::TxtRecord,
+};
+
 #[derive(Debug, Clone, Error)]
 pub enum ServiceError {
     #[error("Service type has more than 63 characters")]
@@ -13,6 +19,8 @@ pub enum ServiceError {
     ShortKind,
     #[error("Service type contains invalid characters")]
     InvalidChar,
+    #[error("Service name must look like Instance._type._proto[.domain]")]
+    Malformed,
 }
 
 #[derive(
@@ -41,6 +49,9 @@ impl FromStr for ServiceKind {
 
         let prefix = chars.next().unwrap();
         if prefix != '_' {
+            if !is_valid(prefix) {
+                return Err(ServiceError::InvalidChar);
+            }
             buf.push(prefix);
         }
 
@@ -101,12 +112,79 @@ impl fmt::Display for TransportProtocol {
     }
 }
 
+/// A fully qualified service instance name, e.g. `My Printer._ipp._tcp.local`.
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, SerializeDisplay, DeserializeFromStr,
+)]
+pub struct ServiceInstance {
+    pub name: String,
+    pub kind: ServiceKind,
+    pub protocol: TransportProtocol,
+    pub domain: Option<String>,
+}
+
+impl ServiceInstance {
+    pub fn service_type(&self) -> String {
+        format!("_{}._{}", self.kind, self.protocol)
+    }
+}
+
+impl FromStr for ServiceInstance {
+    type Err = ServiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // instance names may contain dots themselves,
+        // so search for the first `._type._proto` pair instead of splitting naively
+        let labels = s.split('.').collect::<Vec<_>>();
+
+        let position = labels
+            .windows(2)
+            .skip(1)
+            .position(|pair| pair[0].starts_with('_') && matches!(pair[1], "_tcp" | "_udp"))
+            .ok_or(ServiceError::Malformed)?
+            + 1;
+
+        let name = labels[..position].join(".");
+        let kind = labels[position].parse()?;
+        let protocol = labels[position + 1][1..]
+            .parse()
+            .map_err(|_| ServiceError::Malformed)?;
+        let domain = match labels[position + 2..].join(".") {
+            domain if domain.is_empty() => None,
+            domain => Some(domain),
+        };
+
+        Ok(Self {
+            name,
+            kind,
+            protocol,
+            domain,
+        })
+    }
+}
+
+impl fmt::Display for ServiceInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.name, self.service_type())?;
+
+        if let Some(domain) = &self.domain {
+            write!(f, ".{domain}")?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Service {
     pub name: String,
     pub kinds: Vec<ServiceKind>,
     pub protocol: TransportProtocol,
     pub port: u16,
+    pub txt: TxtRecord,
+    /// TTL of the service records, avahi picks its defaults if unset.
+    pub ttl: Option<Ttl>,
+    pub flags: PublishFlags,
 }
 
 impl Service {
@@ -116,6 +194,9 @@ impl Service {
             kinds: vec![kind],
             protocol,
             port,
+            txt: TxtRecord::new(),
+            ttl: None,
+            flags: PublishFlags::empty(),
         }
     }
 
@@ -130,8 +211,58 @@ impl Service {
             kinds,
             protocol,
             port,
+            txt: TxtRecord::new(),
+            ttl: None,
+            flags: PublishFlags::empty(),
         }
     }
+
+    pub fn service_type(&self) -> String {
+        format!("_{}._{}", self.kinds[0], self.protocol)
+    }
+
+    pub fn with_txt(mut self, txt: TxtRecord) -> Self {
+        self.txt = txt;
+        self
+    }
+
+    pub fn with_ttl(mut self, ttl: Option<Ttl>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn with_flags(mut self, flags: PublishFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Name of the service type in the `.local` domain, e.g. `_https._tcp.local`.
+    pub fn type_name(&self) -> Result<NameBuf, NameError> {
+        let mut name = NameBuf::from_str("local")?;
+        name.prepend_label(&format!("_{}", self.protocol))?;
+        name.prepend_label(&format!("_{}", self.kinds[0]))?;
+        Ok(name)
+    }
+
+    /// Name of the service instance, e.g. `vault._https._tcp.local`.
+    pub fn instance_name(&self) -> Result<NameBuf, NameError> {
+        let mut name = self.type_name()?;
+        name.prepend_raw_label(&self.name)?;
+        Ok(name)
+    }
+
+    /// Names of the sub types browsed for this service, e.g. `_printer._sub._http._tcp.local`.
+    pub fn sub_type_names(&self) -> Result<Vec<NameBuf>, NameError> {
+        self.kinds[1..]
+            .iter()
+            .map(|sub_kind| {
+                let mut name = self.type_name()?;
+                name.prepend_label("_sub")?;
+                name.prepend_label(&format!("_{sub_kind}"))?;
+                Ok(name)
+            })
+            .collect()
+    }
 }
 
 impl fmt::Display for Service {
@@ -141,12 +272,46 @@ impl fmt::Display for Service {
             kinds,
             protocol,
             port,
+            txt,
+            ttl,
+            flags,
         } = self;
 
         write!(
             f,
-            "{name} {{ Type: {}, Port: {port} }}",
+            "{name} {{ Type: {}, Port: {port}, Txt: [{txt}]",
             format_args!("_{}._{}", kinds[0], protocol)
-        )
+        )?;
+        if let Some(ttl) = ttl {
+            write!(f, ", TTL: {ttl}")?;
+        }
+        if !flags.is_empty() {
+            write!(f, ", Flags: {flags}")?;
+        }
+        f.write_str(" }")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{ServiceInstance, TransportProtocol};
+
+    #[test]
+    fn service_instance() {
+        let instance = ServiceInstance::from_str("My.Printer._ipp._tcp.local").unwrap();
+
+        assert_eq!(instance.name, "My.Printer");
+        assert_eq!(instance.kind.as_str(), "ipp");
+        assert_eq!(instance.protocol, TransportProtocol::Tcp);
+        assert_eq!(instance.domain.as_deref(), Some("local"));
+        assert_eq!(instance.to_string(), "My.Printer._ipp._tcp.local");
+
+        let instance = ServiceInstance::from_str("vault._https._tcp").unwrap();
+        assert_eq!(instance.domain, None);
+
+        assert!(ServiceInstance::from_str("_https._tcp.local").is_err());
+        assert!(ServiceInstance::from_str("vault._https._sctp").is_err());
     }
 }
@@ -0,0 +1,408 @@
+use std::fmt::{self, Write};
+
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use thiserror::Error;
+
+/// Every entry is prefixed by a single length byte.
+pub const MAX_ENTRY_LEN: usize = 255;
+
+/// Larger records are not recommended as they no longer fit into a single packet,
+/// see [RFC 6763](https://datatracker.ietf.org/doc/html/rfc6763#section-6.2).
+pub const MAX_RECORD_LEN: usize = 1300;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TxtError {
+    #[error("Empty TXT key not allowed")]
+    EmptyKey,
+    #[error("TXT key {0:?} contains invalid characters")]
+    InvalidKey(String),
+    #[error("TXT key {0:?} is defined more than once")]
+    DuplicateKey(String),
+    #[error("TXT entry {0:?} has more than {MAX_ENTRY_LEN} bytes")]
+    LongEntry(String),
+    #[error("TXT record has more than {MAX_RECORD_LEN} bytes")]
+    LongRecord,
+}
+
+/// A single `key[=value]` entry of a DNS-SD TXT record as described in
+/// [RFC 6763](https://datatracker.ietf.org/doc/html/rfc6763#section-6).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TxtEntry {
+    key: String,
+    value: Option<Vec<u8>>,
+}
+
+impl TxtEntry {
+    /// Creates a new entry, `None` as value creates a boolean attribute.
+    pub fn new(key: impl Into<String>, value: Option<Vec<u8>>) -> Result<Self, TxtError> {
+        let key = key.into();
+
+        if key.is_empty() {
+            return Err(TxtError::EmptyKey);
+        } else if !key.bytes().all(|b| (0x20..=0x7e).contains(&b) && b != b'=') {
+            return Err(TxtError::InvalidKey(key));
+        }
+
+        let entry = Self { key, value };
+        if entry.len() > MAX_ENTRY_LEN {
+            return Err(TxtError::LongEntry(entry.key));
+        }
+
+        Ok(entry)
+    }
+
+    pub fn flag(key: impl Into<String>) -> Result<Self, TxtError> {
+        Self::new(key, None)
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns `None` for boolean attributes which carry no `=` at all.
+    pub fn value(&self) -> Option<&[u8]> {
+        self.value.as_deref()
+    }
+
+    /// Length of the encoded entry without its length prefix.
+    pub fn len(&self) -> usize {
+        match &self.value {
+            Some(value) => self.key.len() + 1 + value.len(),
+            None => self.key.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.len());
+        buf.extend(self.key.as_bytes());
+
+        if let Some(value) = &self.value {
+            buf.push(b'=');
+            buf.extend(value);
+        }
+
+        buf
+    }
+
+    /// Decodes an entry as received on the wire.
+    /// Keys are not validated, as peers do not always follow the rfc.
+    pub fn decode(bytes: &[u8]) -> Self {
+        match bytes.iter().position(|&b| b == b'=') {
+            Some(i) => Self {
+                key: String::from_utf8_lossy(&bytes[..i]).into_owned(),
+                value: Some(bytes[i + 1..].to_vec()),
+            },
+            None => Self {
+                key: String::from_utf8_lossy(bytes).into_owned(),
+                value: None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for TxtEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.key)?;
+
+        let Some(value) = &self.value else {
+            return Ok(());
+        };
+        f.write_char('=')?;
+
+        match std::str::from_utf8(value) {
+            Ok(value) => {
+                for c in value.chars() {
+                    if c.is_control() || c == '\\' {
+                        write!(f, "{}", c.escape_default())?;
+                    } else {
+                        f.write_char(c)?;
+                    }
+                }
+            }
+            Err(_) => {
+                for &b in value {
+                    if b == b'\\' {
+                        f.write_str("\\\\")?;
+                    } else if b.is_ascii_graphic() || b == b' ' {
+                        f.write_char(b as char)?;
+                    } else {
+                        write!(f, "\\x{b:02x}")?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An ordered set of [`TxtEntry`] with case-insensitive unique keys.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TxtRecord(Vec<TxtEntry>);
+
+impl TxtRecord {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, entry: TxtEntry) -> Result<(), TxtError> {
+        if self.get(entry.key()).is_some() {
+            return Err(TxtError::DuplicateKey(entry.key));
+        }
+        if self.len() + 1 + entry.len() > MAX_RECORD_LEN {
+            return Err(TxtError::LongRecord);
+        }
+
+        self.0.push(entry);
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&TxtEntry> {
+        self.0
+            .iter()
+            .find(|entry| entry.key.eq_ignore_ascii_case(key))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TxtEntry> {
+        self.0.iter()
+    }
+
+    /// Length of the encoded record including the length prefixes.
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|entry| 1 + entry.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn encode(&self) -> Vec<Vec<u8>> {
+        self.0.iter().map(TxtEntry::encode).collect()
+    }
+
+    pub fn decode(entries: &[Vec<u8>]) -> Self {
+        Self(
+            entries
+                .iter()
+                .map(|entry| TxtEntry::decode(entry))
+                .collect(),
+        )
+    }
+}
+
+impl TryFrom<Vec<TxtEntry>> for TxtRecord {
+    type Error = TxtError;
+
+    fn try_from(entries: Vec<TxtEntry>) -> Result<Self, Self::Error> {
+        let mut record = Self::new();
+        for entry in entries {
+            record.insert(entry)?;
+        }
+        Ok(record)
+    }
+}
+
+impl<'a> IntoIterator for &'a TxtRecord {
+    type IntoIter = std::slice::Iter<'a, TxtEntry>;
+    type Item = &'a TxtEntry;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl fmt::Display for TxtRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut iter = self.iter();
+
+        if let Some(first) = iter.next() {
+            write!(f, "{first}")?;
+        }
+        for entry in iter {
+            write!(f, " {entry}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Serialized as a table where boolean attributes are `true`,
+/// utf-8 values are strings and any other value is an array of bytes.
+impl Serialize for TxtRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+
+        for entry in &self.0 {
+            match entry.value() {
+                None => map.serialize_entry(entry.key(), &true)?,
+                Some(value) => match std::str::from_utf8(value) {
+                    Ok(value) => map.serialize_entry(entry.key(), value)?,
+                    Err(_) => map.serialize_entry(entry.key(), value)?,
+                },
+            }
+        }
+
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for TxtRecord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(TxtRecordVisitor)
+    }
+}
+
+struct TxtRecordVisitor;
+
+impl<'de> Visitor<'de> for TxtRecordVisitor {
+    type Value = TxtRecord;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a table of TXT keys")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut record = TxtRecord::new();
+
+        while let Some((key, value)) = map.next_entry::<String, TxtValue>()? {
+            let value = match value {
+                TxtValue::Flag(false) => continue,
+                TxtValue::Flag(true) => None,
+                TxtValue::Bytes(bytes) => Some(bytes),
+            };
+            let entry = TxtEntry::new(key, value).map_err(de::Error::custom)?;
+            record.insert(entry).map_err(de::Error::custom)?;
+        }
+
+        Ok(record)
+    }
+}
+
+enum TxtValue {
+    Flag(bool),
+    Bytes(Vec<u8>),
+}
+
+impl<'de> Deserialize<'de> for TxtValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(TxtValueVisitor)
+    }
+}
+
+struct TxtValueVisitor;
+
+impl<'de> Visitor<'de> for TxtValueVisitor {
+    type Value = TxtValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a boolean, string, integer or array of bytes")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(TxtValue::Flag(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(TxtValue::Bytes(v.to_string().into_bytes()))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(TxtValue::Bytes(v.to_string().into_bytes()))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(TxtValue::Bytes(v.as_bytes().to_vec()))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(TxtValue::Bytes(v.to_vec()))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(b) = seq.next_element()? {
+            bytes.push(b);
+        }
+        Ok(TxtValue::Bytes(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TxtEntry, TxtError, TxtRecord};
+
+    #[test]
+    fn decode() {
+        let entry = TxtEntry::decode(b"path=/a b");
+        assert_eq!(entry.key(), "path");
+        assert_eq!(entry.value(), Some(&b"/a b"[..]));
+        assert_eq!(entry.to_string(), "path=/a b");
+
+        let entry = TxtEntry::decode(b"flag");
+        assert_eq!(entry.value(), None);
+        assert_eq!(entry.to_string(), "flag");
+
+        let entry = TxtEntry::decode(b"empty=");
+        assert_eq!(entry.to_string(), "empty=");
+
+        let entry = TxtEntry::decode(b"pk=\xff\xfe\\");
+        assert_eq!(entry.to_string(), "pk=\\xff\\xfe\\\\");
+    }
+
+    #[test]
+    fn validate() {
+        assert_eq!(TxtEntry::flag(""), Err(TxtError::EmptyKey));
+        assert!(matches!(
+            TxtEntry::flag("a=b"),
+            Err(TxtError::InvalidKey(_))
+        ));
+        assert!(matches!(
+            TxtEntry::new("key", Some(vec![0; 252])),
+            Err(TxtError::LongEntry(_))
+        ));
+        assert!(TxtEntry::new("key", Some(vec![0; 251])).is_ok());
+
+        let mut record = TxtRecord::new();
+        record.insert(TxtEntry::flag("Path").unwrap()).unwrap();
+        assert!(matches!(
+            record.insert(TxtEntry::flag("path").unwrap()),
+            Err(TxtError::DuplicateKey(_))
+        ));
+
+        for i in 0..5 {
+            let entry = TxtEntry::new(format!("k{i}"), Some(vec![0; 251])).unwrap();
+            record.insert(entry).unwrap();
+        }
+        let entry = TxtEntry::new("k5", Some(vec![0; 251])).unwrap();
+        assert_eq!(record.insert(entry), Err(TxtError::LongRecord));
+    }
+
+    #[test]
+    fn toml() {
+        let record: TxtRecord =
+            toml::from_str("path = \"/\"\nhidden = false\npairing = true\nver = 2\npk = [255, 0]")
+                .unwrap();
+
+        assert_eq!(
+            record.encode(),
+            vec![
+                b"path=/".to_vec(),
+                b"pairing".to_vec(),
+                b"ver=2".to_vec(),
+                b"pk=\xff\0".to_vec()
+            ]
+        );
+        assert_eq!(
+            toml::from_str::<TxtRecord>(&toml::to_string(&record).unwrap()).unwrap(),
+            record
+        );
+    }
+}